@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS api_tokens
+(
+    id         SERIAL UNIQUE PRIMARY KEY,
+    name       Text      NOT NULL,
+    token_hash Text      NOT NULL UNIQUE,
+    admin      boolean   NOT NULL DEFAULT false,
+    created    TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX events_chat_id_active_idx;
+
+ALTER TABLE events
+    ADD CONSTRAINT events_chat_id_active_key UNIQUE (chat_id, active);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE events
+    DROP CONSTRAINT IF EXISTS events_chat_id_active_key;
+
+-- At most one active event per chat, any number of inactive ones
+CREATE UNIQUE INDEX events_chat_id_active_idx ON events (chat_id) WHERE active;
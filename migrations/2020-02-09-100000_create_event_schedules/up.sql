@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS event_schedules
+(
+    id       SERIAL UNIQUE PRIMARY KEY,
+    chat_id  Integer  NOT NULL,
+    -- 0 is monday, 6 is sunday
+    weekday  SMALLINT NOT NULL CHECK (weekday BETWEEN 0 AND 6),
+    time     TIME     NOT NULL,
+    -- IANA name like `Europe/Berlin`
+    timezone Text     NOT NULL,
+    UNIQUE (chat_id, weekday, time),
+    FOREIGN KEY (chat_id) REFERENCES chats (id)
+)
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE api_tokens
+    ALTER COLUMN created TYPE TIMESTAMP USING created AT TIME ZONE 'UTC';
+
+ALTER TABLE messages
+    ALTER COLUMN timestamp TYPE TIMESTAMP USING timestamp AT TIME ZONE 'UTC',
+    ALTER COLUMN edit_timestamp TYPE TIMESTAMP USING edit_timestamp AT TIME ZONE 'UTC';
+
+ALTER TABLE events
+    ALTER COLUMN timestamp TYPE TIMESTAMP USING timestamp AT TIME ZONE 'UTC';
+
+ALTER TABLE chats
+    DROP COLUMN timezone;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- IANA name like `Europe/Berlin`, timestamps of the chat are returned in it
+ALTER TABLE chats
+    ADD COLUMN timezone Text NOT NULL DEFAULT 'UTC';
+
+-- Existing timestamps were all stored as UTC
+ALTER TABLE events
+    ALTER COLUMN timestamp TYPE TIMESTAMPTZ USING timestamp AT TIME ZONE 'UTC';
+
+ALTER TABLE messages
+    ALTER COLUMN timestamp TYPE TIMESTAMPTZ USING timestamp AT TIME ZONE 'UTC',
+    ALTER COLUMN edit_timestamp TYPE TIMESTAMPTZ USING edit_timestamp AT TIME ZONE 'UTC';
+
+ALTER TABLE api_tokens
+    ALTER COLUMN created TYPE TIMESTAMPTZ USING created AT TIME ZONE 'UTC';
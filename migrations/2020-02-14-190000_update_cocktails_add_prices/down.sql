@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE chats
+    DROP COLUMN bill_rule;
+
+ALTER TABLE cocktails
+    DROP COLUMN price,
+    DROP COLUMN jumbo_price;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Prices are in cents, cocktails without a price are left out of bills
+ALTER TABLE cocktails
+    ADD COLUMN price       Integer CHECK (price >= 0),
+    ADD COLUMN jumbo_price Integer CHECK (jumbo_price >= 0);
+
+-- 0 is everyone pays their own drinks, see `models::BillRule`
+ALTER TABLE chats
+    ADD COLUMN bill_rule SMALLINT NOT NULL DEFAULT 0;
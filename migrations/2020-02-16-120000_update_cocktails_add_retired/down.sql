@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE ingredients
+    ALTER COLUMN id DROP DEFAULT;
+DROP SEQUENCE ingredients_id_seq;
+
+ALTER TABLE cocktails
+    DROP COLUMN retired;
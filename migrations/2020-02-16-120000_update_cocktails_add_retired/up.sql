@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Retired cocktails stay around for past rolls but are no longer rolled
+ALTER TABLE cocktails
+    ADD COLUMN retired boolean NOT NULL DEFAULT false;
+
+-- Ingredients were only ever inserted with explicit ids by migrations
+CREATE SEQUENCE ingredients_id_seq OWNED BY ingredients.id;
+SELECT setval('ingredients_id_seq', COALESCE((SELECT MAX(id) FROM ingredients), 0) + 1, false);
+ALTER TABLE ingredients
+    ALTER COLUMN id SET DEFAULT nextval('ingredients_id_seq');
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE events
+    DROP COLUMN menu_id;
+
+ALTER TABLE chats
+    DROP COLUMN menu_id;
+
+ALTER TABLE cocktails
+    DROP COLUMN menu_id;
+
+DROP TABLE menus;
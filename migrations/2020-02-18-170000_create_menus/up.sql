@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS menus
+(
+    id   SERIAL UNIQUE PRIMARY KEY,
+    name Text NOT NULL UNIQUE
+);
+
+-- Holds every cocktail which existed before menus
+INSERT INTO menus (id, name)
+VALUES (1, 'Default');
+SELECT setval('menus_id_seq', 1);
+
+ALTER TABLE cocktails
+    ADD COLUMN menu_id Integer NOT NULL DEFAULT 1 REFERENCES menus (id);
+ALTER TABLE cocktails
+    ALTER COLUMN menu_id DROP DEFAULT;
+
+ALTER TABLE chats
+    ADD COLUMN menu_id Integer NOT NULL DEFAULT 1 REFERENCES menus (id);
+
+-- Overrides the menu of the chat
+ALTER TABLE events
+    ADD COLUMN menu_id Integer REFERENCES menus (id);
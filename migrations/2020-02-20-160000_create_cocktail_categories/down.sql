@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE cocktails
+    DROP CONSTRAINT cocktails_category_fkey,
+    ALTER COLUMN category TYPE SMALLINT,
+    ALTER COLUMN category SET DEFAULT 0;
+
+DROP TABLE cocktail_categories;
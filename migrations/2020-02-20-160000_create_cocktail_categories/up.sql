@@ -0,0 +1,25 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS cocktail_categories
+(
+    id       SERIAL UNIQUE PRIMARY KEY,
+    name     Text    NOT NULL UNIQUE,
+    -- Categories are listed and rolled in ascending order
+    position Integer NOT NULL DEFAULT 0
+);
+
+-- The values of the former CocktailCategory enum
+INSERT INTO cocktail_categories (id, name, position)
+VALUES (0, 'Caipi', 0),
+       (1, 'Jumbo', 1),
+       (2, 'Vodka', 2),
+       (3, 'Coladas', 3),
+       (4, 'Rum', 4),
+       (5, 'Tequila', 5),
+       (6, 'Frozen Margaritas', 6),
+       (7, 'Gin', 7);
+SELECT setval('cocktail_categories_id_seq', 7);
+
+ALTER TABLE cocktails
+    ALTER COLUMN category DROP DEFAULT,
+    ALTER COLUMN category TYPE Integer,
+    ADD CONSTRAINT cocktails_category_fkey FOREIGN KEY (category) REFERENCES cocktail_categories (id);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_excluded_ingredients;
+
+ALTER TABLE users
+    DROP COLUMN non_alcoholic;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+ALTER TABLE users
+    ADD COLUMN non_alcoholic BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS user_excluded_ingredients
+(
+    id            SERIAL UNIQUE PRIMARY KEY,
+    user_id       Integer NOT NULL,
+    ingredient_id Integer NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (ingredient_id) REFERENCES ingredients (id),
+    UNIQUE (user_id, ingredient_id)
+);
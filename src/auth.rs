@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+
+use hmac::{Hmac, Mac};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::http::Status;
+use rocket::Outcome;
+use rocket::request::{self, FormItems, FromRequest, Request};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::database::models::{ApiToken, User};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TOKEN_LENGTH: usize = 48;
+
+/// Seconds after which signed login data from telegram is no longer accepted.
+const MAX_AUTH_AGE: i64 = 24 * 60 * 60;
+
+/// Header a bot uses to name the telegram user it is acting on behalf of.
+pub const TELEGRAM_USER_HEADER: &str = "X-Telegram-User-Id";
+
+/// Private cookie holding the id of the logged in user.
+pub const SESSION_COOKIE: &str = "user_id";
+
+/// Token of the telegram bot login data is signed with, logins are disabled without it.
+pub struct TelegramBotToken(pub Option<String>);
+
+/// Whoever is making the current request.
+#[derive(Debug)]
+pub enum Principal {
+    Anonymous,
+    Token {
+        api_token: ApiToken,
+        /// Telegram id of the user the bot is acting on behalf of
+        telegram_id: Option<i32>,
+    },
+    /// A user logged in through telegram
+    User(User),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthError {
+    MalformedHeader,
+    InvalidToken,
+    InvalidSignature,
+    Expired,
+    Database,
+}
+
+pub fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .collect()
+}
+
+/// Tokens are only ever stored as their SHA-256 hash.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Parses url encoded `key=value` pairs as sent by the login widget or contained in `initData`.
+pub fn parse_fields(data: &str) -> BTreeMap<String, String> {
+    FormItems::from(data)
+        .map(|item| item.key_value_decoded())
+        .collect()
+}
+
+/// Verifies data sent by the telegram login widget and returns the telegram id of the user.
+///
+/// See https://core.telegram.org/widgets/login#checking-authorization
+pub fn verify_login_widget(fields: &BTreeMap<String, String>, bot_token: &str) -> Result<i32, AuthError> {
+    verify_fields(fields, &Sha256::digest(bot_token.as_bytes()))?;
+
+    fields
+        .get("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or(AuthError::InvalidSignature)
+}
+
+#[derive(Deserialize)]
+struct WebAppUser {
+    id: i32,
+}
+
+/// Verifies the `initData` of a telegram web app and returns the telegram id of the user.
+///
+/// See https://core.telegram.org/bots/webapps#validating-data-received-via-the-web-app
+pub fn verify_web_app(fields: &BTreeMap<String, String>, bot_token: &str) -> Result<i32, AuthError> {
+    let mut mac = HmacSha256::new_varkey(b"WebAppData").map_err(|_| AuthError::InvalidSignature)?;
+    mac.input(bot_token.as_bytes());
+    verify_fields(fields, &mac.result().code())?;
+
+    fields
+        .get("user")
+        .and_then(|user| serde_json::from_str::<WebAppUser>(user).ok())
+        .map(|user| user.id)
+        .ok_or(AuthError::InvalidSignature)
+}
+
+fn verify_fields(fields: &BTreeMap<String, String>, secret_key: &[u8]) -> Result<(), AuthError> {
+    let hash = fields
+        .get("hash")
+        .and_then(|hash| hex::decode(hash).ok())
+        .ok_or(AuthError::InvalidSignature)?;
+
+    // `BTreeMap` already iterates in the alphabetical order telegram expects
+    let data_check_string = fields
+        .iter()
+        .filter(|(key, _)| key.as_str() != "hash")
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut mac = HmacSha256::new_varkey(secret_key).map_err(|_| AuthError::InvalidSignature)?;
+    mac.input(data_check_string.as_bytes());
+    mac.verify(&hash).map_err(|_| AuthError::InvalidSignature)?;
+
+    let auth_date: i64 = fields
+        .get("auth_date")
+        .and_then(|auth_date| auth_date.parse().ok())
+        .ok_or(AuthError::InvalidSignature)?;
+    if chrono::Utc::now().timestamp() - auth_date > MAX_AUTH_AGE {
+        return Err(AuthError::Expired);
+    }
+
+    Ok(())
+}
+
+/// Credentials sent with a request.
+///
+/// They are only checked once the request handler holds a database connection,
+/// so a request never occupies more than one connection of the pool.
+pub struct Credentials {
+    token: Option<String>,
+    /// Telegram id of the user the bot is acting on behalf of
+    telegram_id: Option<i32>,
+    /// Id of the user logged in through the session cookie
+    user_id: Option<i32>,
+}
+
+impl Credentials {
+    /// Requests with a bearer token are authenticated by the token, invalid tokens are rejected.
+    /// The telegram user a bot acts on behalf of is only taken into account for those requests.
+    ///
+    /// Requests without an `Authorization` header fall back to the session cookie and are anonymous without one.
+    pub fn resolve(self, connection: &diesel::PgConnection) -> Result<Principal, AuthError> {
+        if let Some(token) = self.token {
+            return match ApiToken::get_by_token_hash(&hash_token(&token), connection) {
+                Ok(Some(api_token)) => Ok(Principal::Token { api_token, telegram_id: self.telegram_id }),
+                Ok(None) => Err(AuthError::InvalidToken),
+                Err(_) => Err(AuthError::Database),
+            };
+        }
+
+        match self.user_id.map(|user_id| User::get_by_id(user_id, connection)) {
+            Some(Ok(user)) => Ok(Principal::User(user)),
+            // The user has been deleted since logging in
+            None | Some(Err(diesel::NotFound)) => Ok(Principal::Anonymous),
+            Some(Err(_)) => Err(AuthError::Database),
+        }
+    }
+}
+
+/// Only checks the format of the credentials, see `Credentials::resolve`.
+impl<'a, 'r> FromRequest<'a, 'r> for Credentials {
+    type Error = AuthError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        const PREFIX: &str = "Bearer ";
+        let token = match request.headers().get_one("Authorization") {
+            Some(header) if header.starts_with(PREFIX) => Some(header[PREFIX.len()..].trim().to_owned()),
+            Some(_) => return Outcome::Failure((Status::Unauthorized, AuthError::MalformedHeader)),
+            None => None,
+        };
+
+        let telegram_id = match request.headers().get_one(TELEGRAM_USER_HEADER).map(str::parse::<i32>) {
+            Some(Ok(telegram_id)) => Some(telegram_id),
+            Some(Err(_)) => return Outcome::Failure((Status::BadRequest, AuthError::MalformedHeader)),
+            None => None,
+        };
+
+        let user_id = request
+            .cookies()
+            .get_private(SESSION_COOKIE)
+            .and_then(|cookie| cookie.value().parse::<i32>().ok());
+
+        Outcome::Success(Credentials { token, telegram_id, user_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use hmac::Mac;
+    use sha2::{Digest, Sha256};
+
+    use super::{AuthError, HmacSha256, MAX_AUTH_AGE, parse_fields, verify_login_widget, verify_web_app};
+
+    const BOT_TOKEN: &str = "123456:ABC-DEF1234ghIkl-zyx57W2v1u123ew11";
+
+    /// Signs `fields` the way telegram does and adds the resulting `hash`.
+    fn sign(mut fields: BTreeMap<String, String>, secret_key: &[u8]) -> BTreeMap<String, String> {
+        let data_check_string = fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut mac = HmacSha256::new_varkey(secret_key).unwrap();
+        mac.input(data_check_string.as_bytes());
+        fields.insert("hash".to_owned(), hex::encode(mac.result().code()));
+
+        fields
+    }
+
+    fn login_widget_fields(auth_date: i64) -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::new();
+        fields.insert("id".to_owned(), "42".to_owned());
+        fields.insert("first_name".to_owned(), "Dicer".to_owned());
+        fields.insert("auth_date".to_owned(), auth_date.to_string());
+
+        sign(fields, &Sha256::digest(BOT_TOKEN.as_bytes()))
+    }
+
+    fn web_app_secret_key() -> Vec<u8> {
+        let mut mac = HmacSha256::new_varkey(b"WebAppData").unwrap();
+        mac.input(BOT_TOKEN.as_bytes());
+
+        mac.result().code().to_vec()
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[test]
+    fn accepts_valid_login_widget_data() {
+        assert_eq!(verify_login_widget(&login_widget_fields(now()), BOT_TOKEN), Ok(42));
+    }
+
+    #[test]
+    fn rejects_tampered_fields() {
+        let mut fields = login_widget_fields(now());
+        fields.insert("id".to_owned(), "43".to_owned());
+
+        assert_eq!(verify_login_widget(&fields, BOT_TOKEN), Err(AuthError::InvalidSignature));
+    }
+
+    #[test]
+    fn rejects_wrong_hashes() {
+        let mut fields = login_widget_fields(now());
+        fields.insert("hash".to_owned(), "00".repeat(32));
+        assert_eq!(verify_login_widget(&fields, BOT_TOKEN), Err(AuthError::InvalidSignature));
+
+        fields.insert("hash".to_owned(), "not hex".to_owned());
+        assert_eq!(verify_login_widget(&fields, BOT_TOKEN), Err(AuthError::InvalidSignature));
+
+        fields.remove("hash");
+        assert_eq!(verify_login_widget(&fields, BOT_TOKEN), Err(AuthError::InvalidSignature));
+    }
+
+    #[test]
+    fn rejects_data_signed_with_another_token() {
+        let fields = login_widget_fields(now());
+
+        assert_eq!(verify_login_widget(&fields, "654321:other"), Err(AuthError::InvalidSignature));
+    }
+
+    #[test]
+    fn rejects_stale_auth_date() {
+        let fields = login_widget_fields(now() - MAX_AUTH_AGE - 60);
+
+        assert_eq!(verify_login_widget(&fields, BOT_TOKEN), Err(AuthError::Expired));
+    }
+
+    #[test]
+    fn accepts_url_encoded_web_app_init_data() {
+        let mut fields = BTreeMap::new();
+        fields.insert("query_id".to_owned(), "AAHdF6IQAAAAAN0XohDhrOrc".to_owned());
+        fields.insert("user".to_owned(), r#"{"id":42,"first_name":"Dicer","language_code":"en"}"#.to_owned());
+        fields.insert("auth_date".to_owned(), now().to_string());
+        let fields = sign(fields, &web_app_secret_key());
+
+        let init_data = format!(
+            "query_id={}&user={}&auth_date={}&hash={}",
+            fields["query_id"],
+            "%7B%22id%22%3A42%2C%22first_name%22%3A%22Dicer%22%2C%22language_code%22%3A%22en%22%7D",
+            fields["auth_date"],
+            fields["hash"],
+        );
+
+        assert_eq!(verify_web_app(&parse_fields(&init_data), BOT_TOKEN), Ok(42));
+    }
+
+    #[test]
+    fn rejects_web_app_data_signed_as_login_widget() {
+        let mut fields = BTreeMap::new();
+        fields.insert("user".to_owned(), r#"{"id":42}"#.to_owned());
+        fields.insert("auth_date".to_owned(), now().to_string());
+        let fields = sign(fields, &Sha256::digest(BOT_TOKEN.as_bytes()));
+
+        assert_eq!(verify_web_app(&fields, BOT_TOKEN), Err(AuthError::InvalidSignature));
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use diesel::dsl::any;
+use diesel::prelude::*;
+
+use crate::database::models as db_models;
+use crate::database::schema;
+use crate::graphql::Context;
+use crate::models::{BillRule, Event, EventUser, UserRoll};
+
+type DieselResult<T> = Result<T, diesel::result::Error>;
+
+#[derive(Debug, GraphQLObject)]
+#[graphql(Context = Context)]
+pub struct AttendeeSummary {
+    pub event_user: EventUser,
+    pub rolls: Vec<UserRoll>,
+    #[graphql(description = "Price of the drinks the attendee rolled, in cents")]
+    pub consumed: i32,
+    #[graphql(description = "Share of the bill the attendee pays, in cents")]
+    pub pays: i32,
+}
+
+#[derive(Debug, GraphQLObject)]
+#[graphql(Context = Context)]
+pub struct EventSummary {
+    pub event: Event,
+    pub rule: BillRule,
+    #[graphql(description = "Everyone who attended or rolled")]
+    pub attendees: Vec<AttendeeSummary>,
+    #[graphql(description = "Price of all drinks, in cents")]
+    pub total: i32,
+    #[graphql(description = "Rolled drinks without a price, they are not part of the bill")]
+    pub unpriced_drinks: Vec<String>,
+}
+
+impl EventSummary {
+    /// Splits the bill of the event according to `rule`, or the rule of its chat if none is given.
+    pub fn get(event_id: i32, rule: Option<BillRule>, connection: &diesel::PgConnection) -> DieselResult<EventSummary> {
+        let event = Event::get_by_id(event_id, connection)?;
+        let rule = rule.unwrap_or(event.chat.bill_rule);
+
+        let mut rolls: HashMap<i32, Vec<UserRoll>> = HashMap::new();
+        for user_roll in UserRoll::get_by_event_id(event_id, connection)? {
+            rolls.entry(user_roll.event_user.id).or_default().push(user_roll);
+        }
+
+        let mut event_users: Vec<EventUser> = EventUser::get_by_event_id(event_id, connection)?
+            .into_iter()
+            .filter(|event_user| event_user.attends || rolls.contains_key(&event_user.id))
+            .collect();
+        event_users.sort_by_key(|event_user| event_user.id);
+
+        let prices = get_prices(event.effective_menu_id(), rolls.values().flatten(), connection)?;
+        let mut unpriced_drinks = vec![];
+        let mut attendees: Vec<AttendeeSummary> = event_users
+            .into_iter()
+            .map(|event_user| {
+                let rolls = rolls.remove(&event_user.id).unwrap_or_default();
+                let mut consumed = 0;
+                for user_roll in &rolls {
+                    match price(&prices, user_roll) {
+                        Some(price) => consumed += price,
+                        None => unpriced_drinks.push(user_roll.drink.clone()),
+                    }
+                }
+
+                AttendeeSummary {
+                    event_user,
+                    rolls,
+                    consumed,
+                    pays: 0,
+                }
+            })
+            .collect();
+
+        let total = attendees.iter().map(|attendee| attendee.consumed).sum();
+        let consumed: Vec<i32> = attendees.iter().map(|attendee| attendee.consumed).collect();
+        let lowest_rolls: Vec<Option<i32>> = attendees
+            .iter()
+            .map(|attendee| attendee.rolls.iter().map(|user_roll| user_roll.roll).min())
+            .collect();
+        for (attendee, pays) in attendees.iter_mut().zip(shares(rule, &consumed, &lowest_rolls)) {
+            attendee.pays = pays;
+        }
+
+        unpriced_drinks.sort();
+        unpriced_drinks.dedup();
+
+        Ok(EventSummary {
+            event,
+            rule,
+            attendees,
+            total,
+            unpriced_drinks,
+        })
+    }
+}
+
+/// Prices of all rolled drinks keyed by name, as `(price, jumbo_price)`.
+fn get_prices<'a, I: Iterator<Item=&'a UserRoll>>(menu_id: i32, rolls: I, connection: &diesel::PgConnection) -> DieselResult<HashMap<String, (Option<i32>, Option<i32>)>> {
+    let names: Vec<String> = rolls.map(|user_roll| user_roll.drink.clone()).collect();
+
+    Ok(schema::cocktails::table
+        .filter(schema::cocktails::dsl::menu_id.eq(menu_id))
+        .filter(schema::cocktails::dsl::name.eq(any(names)))
+        .load::<db_models::Cocktail>(connection)?
+        .into_iter()
+        .map(|cocktail| (cocktail.name, (cocktail.price, cocktail.jumbo_price)))
+        .collect())
+}
+
+/// Jumbo rolls fall back to the regular price if the cocktail has no jumbo price.
+fn price(prices: &HashMap<String, (Option<i32>, Option<i32>)>, user_roll: &UserRoll) -> Option<i32> {
+    let (price, jumbo_price) = prices.get(&user_roll.drink)?;
+
+    if user_roll.jumbo {
+        jumbo_price.or(*price)
+    } else {
+        *price
+    }
+}
+
+/// Share of the bill each attendee pays, given what they consumed and the lowest value they rolled.
+fn shares(rule: BillRule, consumed: &[i32], lowest_rolls: &[Option<i32>]) -> Vec<i32> {
+    let payers: Vec<usize> = match rule {
+        BillRule::OWN_DRINKS => return consumed.to_vec(),
+        BillRule::SPLIT_EVENLY => (0..consumed.len()).collect(),
+        BillRule::LOWEST_ROLL_PAYS => {
+            let lowest = lowest_rolls.iter().filter_map(|roll| *roll).min();
+
+            (0..consumed.len())
+                .filter(|index| lowest.is_some() && lowest_rolls[*index] == lowest)
+                .collect()
+        }
+    };
+
+    let mut shares = vec![0; consumed.len()];
+    for (index, share) in payers.iter().zip(split(consumed.iter().sum(), payers.len())) {
+        shares[*index] = share;
+    }
+
+    shares
+}
+
+/// Splits `total` into `parts` shares, the remaining cents go to the first shares.
+fn split(total: i32, parts: usize) -> Vec<i32> {
+    if parts == 0 {
+        return vec![];
+    }
+
+    let parts = parts as i32;
+    (0..parts)
+        .map(|index| total / parts + if index < total % parts { 1 } else { 0 })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::BillRule;
+
+    use super::{shares, split};
+
+    #[test]
+    fn split_gives_remaining_cents_to_the_first_shares() {
+        assert_eq!(split(1000, 3), vec![334, 333, 333]);
+        assert_eq!(split(1001, 2), vec![501, 500]);
+        assert_eq!(split(5, 7), vec![1, 1, 1, 1, 1, 0, 0]);
+        assert_eq!(split(900, 3), vec![300, 300, 300]);
+    }
+
+    #[test]
+    fn split_without_parts_is_empty() {
+        assert_eq!(split(1000, 0), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn own_drinks_pay_what_they_consumed() {
+        assert_eq!(shares(BillRule::OWN_DRINKS, &[450, 0, 700], &[Some(3), None, Some(1)]), vec![450, 0, 700]);
+    }
+
+    #[test]
+    fn split_evenly_includes_attendees_without_rolls() {
+        assert_eq!(shares(BillRule::SPLIT_EVENLY, &[450, 0, 551], &[Some(3), None, Some(1)]), vec![334, 334, 333]);
+    }
+
+    #[test]
+    fn lowest_roll_pays_everything() {
+        assert_eq!(shares(BillRule::LOWEST_ROLL_PAYS, &[450, 300, 700], &[Some(3), None, Some(2)]), vec![0, 0, 1450]);
+    }
+
+    #[test]
+    fn lowest_roll_ties_split_the_bill() {
+        assert_eq!(shares(BillRule::LOWEST_ROLL_PAYS, &[500, 701, 300], &[Some(2), Some(1), Some(1)]), vec![0, 751, 750]);
+    }
+
+    #[test]
+    fn nobody_pays_without_rolls() {
+        assert_eq!(shares(BillRule::LOWEST_ROLL_PAYS, &[0, 0], &[None, None]), vec![0, 0]);
+    }
+
+    #[test]
+    fn empty_events_have_no_shares() {
+        for rule in &[BillRule::OWN_DRINKS, BillRule::SPLIT_EVENLY, BillRule::LOWEST_ROLL_PAYS] {
+            assert_eq!(shares(*rule, &[], &[]), Vec::<i32>::new());
+        }
+    }
+}
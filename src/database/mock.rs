@@ -1,9 +1,10 @@
 use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
 
-use chrono::NaiveDateTime;
+use chrono::{DateTime, TimeZone, Utc};
 
 use crate::database::models::*;
+use crate::models::{ChatType, KeyboardType};
 
 pub trait Mockable {
     type Item;
@@ -47,16 +48,24 @@ fn random_roll() -> u8 {
     (rand::random::<u8>() % 6) + 1
 }
 
-fn random_chat_type() -> u8 {
-    rand::random::<u8>() % 3
+fn random_chat_type() -> ChatType {
+    match rand::random::<u8>() % 3 {
+        0 => ChatType::PRIVATE,
+        1 => ChatType::GROUP,
+        _ => ChatType::SUPERGROUP,
+    }
 }
 
-fn random_keyboard_type() -> u8 {
-    rand::random::<u8>() % 3
+fn random_keyboard_type() -> KeyboardType {
+    match rand::random::<u8>() % 3 {
+        0 => KeyboardType::NONE,
+        1 => KeyboardType::ATTEND,
+        _ => KeyboardType::DICE,
+    }
 }
 
-fn random_date_time() -> NaiveDateTime {
-    NaiveDateTime::from_timestamp(rand::random::<u32>() as i64, 0)
+fn random_date_time() -> DateTime<Utc> {
+    Utc.timestamp(rand::random::<u32>() as i64, 0)
 }
 
 impl Mockable for ChatI {
@@ -64,12 +73,13 @@ impl Mockable for ChatI {
 
     fn mock(_: Option<HashMap<String, i32, RandomState>>) -> Option<Self::Item> {
         Some(ChatI {
-            chat_type: random_chat_type() as i16,
+            chat_type: random_chat_type(),
             telegram_id: random_i32(),
             title: random_string(16),
             description: random_optional_string(64),
-            current_keyboard: random_keyboard_type() as i16,
+            current_keyboard: random_keyboard_type(),
             spam_detection: random_bool(),
+            timezone: None,
         })
     }
 }
@@ -104,6 +114,7 @@ impl Mockable for EventI {
             chat_id: *extra_data?.get("chat_id")?,
             timestamp: random_date_time(),
             active: random_bool(),
+            menu_id: None,
         })
     }
 }
@@ -1,32 +1,75 @@
-use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveTime, Utc};
+use diesel::dsl::any;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 
 use crate::database::schema::{self, *};
-use crate::graphql::schema::Constraints;
+use crate::graphql::order::{ApiTokenOrder, ApiTokenOrderField, CocktailOrder, CocktailOrderField, IngredientOrder, IngredientOrderField, MenuOrder, MenuOrderField};
+use crate::graphql::schema::{CocktailFilter, Constraints};
 use crate::models;
+use crate::then_order_by;
 
 type DieselResult<T> = Result<T, diesel::result::Error>;
 
+#[derive(Debug, GraphQLObject, Identifiable, Queryable)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    #[graphql(skip)]
+    pub token_hash: String,
+    pub admin: bool,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug, Insertable)]
+#[table_name = "api_tokens"]
+pub struct ApiTokenI {
+    pub name: String,
+    pub token_hash: String,
+    pub admin: bool,
+}
+
 #[derive(Associations, Debug, Identifiable, Queryable)]
 pub struct Chat {
     pub id: i32,
-    pub chat_type: i16,
+    pub chat_type: models::ChatType,
     pub telegram_id: i32,
     pub title: String,
     pub description: Option<String>,
-    pub current_keyboard: i16,
+    pub current_keyboard: models::KeyboardType,
     pub spam_detection: bool,
+    pub timezone: String,
+    pub bill_rule: models::BillRule,
+    pub menu_id: i32,
 }
 
 #[derive(AsChangeset, Debug, Insertable)]
 #[table_name = "chats"]
 pub struct ChatI {
-    pub chat_type: i16,
+    pub chat_type: models::ChatType,
     pub telegram_id: i32,
     pub title: String,
     pub description: Option<String>,
-    pub current_keyboard: i16,
+    pub current_keyboard: models::KeyboardType,
     pub spam_detection: bool,
+    /// New chats default to UTC, existing ones keep their timezone if this is `None`
+    pub timezone: Option<String>,
+}
+
+/// `Some(None)` clears a nullable column, `None` leaves it untouched.
+#[derive(AsChangeset, Debug)]
+#[table_name = "chats"]
+pub struct ChatPatch {
+    pub chat_type: Option<models::ChatType>,
+    pub title: Option<String>,
+    pub description: Option<Option<String>>,
+    pub current_keyboard: Option<models::KeyboardType>,
+    pub spam_detection: Option<bool>,
+    pub timezone: Option<String>,
+    pub bill_rule: Option<models::BillRule>,
+    pub menu_id: Option<i32>,
 }
 
 #[derive(Associations, Debug, Identifiable, Queryable)]
@@ -51,21 +94,67 @@ pub struct ChatUserI {
     pub admin: bool,
 }
 
-// unique(chat_id, active)
+#[derive(AsChangeset, Debug, GraphQLInputObject)]
+#[table_name = "chat_users"]
+pub struct ChatUserPatch {
+    pub spamming: Option<bool>,
+    pub muted: Option<bool>,
+    pub admin: Option<bool>,
+}
+
+// unique(chat_id) where active
 #[derive(Associations, Debug, Identifiable, Queryable)]
 pub struct Event {
     pub id: i32,
     pub chat_id: i32,
-    pub timestamp: NaiveDateTime,
+    pub timestamp: DateTime<Utc>,
     pub active: bool,
+    pub menu_id: Option<i32>,
 }
 
 #[derive(AsChangeset, Debug, Insertable)]
 #[table_name = "events"]
 pub struct EventI {
     pub chat_id: i32,
-    pub timestamp: NaiveDateTime,
+    pub timestamp: DateTime<Utc>,
     pub active: bool,
+    /// Overrides the menu of the chat
+    pub menu_id: Option<i32>,
+}
+
+/// `Some(None)` clears a nullable column, `None` leaves it untouched.
+#[derive(AsChangeset, Debug)]
+#[table_name = "events"]
+pub struct EventPatch {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub menu_id: Option<Option<i32>>,
+}
+
+#[derive(Associations, Debug, Identifiable, Queryable)]
+#[belongs_to(Chat)]
+pub struct EventSchedule {
+    pub id: i32,
+    pub chat_id: i32,
+    pub weekday: models::Weekday,
+    pub time: NaiveTime,
+    pub timezone: String,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "event_schedules"]
+pub struct EventScheduleI {
+    pub chat_id: i32,
+    pub weekday: models::Weekday,
+    pub time: NaiveTime,
+    pub timezone: String,
+}
+
+#[derive(AsChangeset, Debug)]
+#[table_name = "event_schedules"]
+pub struct EventSchedulePatch {
+    pub weekday: Option<models::Weekday>,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<String>,
 }
 
 #[derive(AsChangeset, Associations, Debug, Identifiable, Queryable)]
@@ -96,6 +185,12 @@ pub struct EventUserI {
     pub attends: bool,
 }
 
+#[derive(AsChangeset, Debug, GraphQLInputObject)]
+#[table_name = "event_users"]
+pub struct EventUserPatch {
+    pub attends: Option<bool>,
+}
+
 #[derive(AsChangeset, Debug, GraphQLInputObject, Insertable)]
 #[table_name = "user_rolls"]
 pub struct UserRollI {
@@ -107,6 +202,15 @@ pub struct UserRollI {
     pub drink: String,
 }
 
+#[derive(AsChangeset, Debug, GraphQLInputObject)]
+#[table_name = "user_rolls"]
+pub struct UserRollPatch {
+    pub jumbo: Option<bool>,
+    pub alcoholic: Option<bool>,
+    pub roll: Option<i32>,
+    pub drink: Option<String>,
+}
+
 #[derive(Associations, Debug, Identifiable, Queryable)]
 #[belongs_to(Chat)]
 #[belongs_to(User, foreign_key = "user_id")]
@@ -116,9 +220,9 @@ pub struct Message {
     pub telegram_id: i32,
     pub chat_id: i32,
     pub user_id: Option<i32>,
-    pub timestamp: NaiveDateTime,
+    pub timestamp: DateTime<Utc>,
     pub reply_to_message_id: Option<i32>,
-    pub edit_timestamp: Option<NaiveDateTime>,
+    pub edit_timestamp: Option<DateTime<Utc>>,
     pub text: Option<String>,
     pub caption: Option<String>,
     pub new_chat_member_ids: Option<Vec<i32>>,
@@ -131,15 +235,15 @@ pub struct Message {
     pub pinned_message_id: Option<i32>,
 }
 
-#[derive(AsChangeset, Debug, Insertable)]
+#[derive(AsChangeset, Debug, GraphQLInputObject, Insertable)]
 #[table_name = "messages"]
 pub struct MessageI {
     pub telegram_id: i32,
     pub chat_id: i32,
     pub user_id: Option<i32>,
-    pub timestamp: NaiveDateTime,
+    pub timestamp: DateTime<Utc>,
     pub reply_to_message_id: Option<i32>,
-    pub edit_timestamp: Option<NaiveDateTime>,
+    pub edit_timestamp: Option<DateTime<Utc>>,
     pub text: Option<String>,
     pub caption: Option<String>,
     pub new_chat_member_ids: Option<Vec<i32>>,
@@ -152,7 +256,7 @@ pub struct MessageI {
     pub pinned_message_id: Option<i32>,
 }
 
-#[derive(AsChangeset, Associations, Debug, GraphQLObject, Identifiable, Insertable, Queryable)]
+#[derive(AsChangeset, Associations, Clone, Debug, Identifiable, Insertable, Queryable)]
 #[table_name = "users"]
 pub struct User {
     pub id: i32,
@@ -162,8 +266,10 @@ pub struct User {
     pub telegram_id: i32,
     pub is_bot: bool,
     pub has_private_conversation: bool,
+    pub non_alcoholic: bool,
 }
 
+/// `non_alcoholic` is left out, so upserting a user doesn't reset their preference.
 #[derive(AsChangeset, Debug, Insertable)]
 #[table_name = "users"]
 pub struct UserI {
@@ -175,21 +281,100 @@ pub struct UserI {
     pub has_private_conversation: bool,
 }
 
+/// `Some(None)` clears a nullable column, `None` leaves it untouched.
+#[derive(AsChangeset, Debug)]
+#[table_name = "users"]
+pub struct UserPatch {
+    pub username: Option<Option<String>>,
+    pub first_name: Option<String>,
+    pub last_name: Option<Option<String>>,
+    pub is_bot: Option<bool>,
+    pub has_private_conversation: Option<bool>,
+    pub non_alcoholic: Option<bool>,
+}
+
 #[derive(Debug, Identifiable, Queryable)]
 pub struct Cocktail {
     pub id: i32,
     pub name: String,
     pub jumbo: bool,
     pub alcoholic: bool,
-    pub category: i16,
+    pub category: i32,
+    /// In cents
+    pub price: Option<i32>,
+    /// In cents
+    pub jumbo_price: Option<i32>,
+    pub retired: bool,
+    pub menu_id: i32,
 }
 
-#[derive(Debug, GraphQLObject, Identifiable, Queryable)]
+#[derive(Debug, Insertable)]
+#[table_name = "cocktails"]
+pub struct CocktailI {
+    pub menu_id: i32,
+    pub name: String,
+    pub jumbo: bool,
+    pub alcoholic: bool,
+    pub category: i32,
+    pub price: Option<i32>,
+    pub jumbo_price: Option<i32>,
+}
+
+/// `Some(None)` clears a nullable column, `None` leaves it untouched.
+#[derive(AsChangeset, Debug)]
+#[table_name = "cocktails"]
+pub struct CocktailPatch {
+    pub menu_id: Option<i32>,
+    pub name: Option<String>,
+    pub jumbo: Option<bool>,
+    pub alcoholic: Option<bool>,
+    pub category: Option<i32>,
+    pub price: Option<Option<i32>>,
+    pub jumbo_price: Option<Option<i32>>,
+    pub retired: Option<bool>,
+}
+
+#[derive(Clone, Debug, GraphQLObject, Identifiable, Queryable)]
+#[table_name = "cocktail_categories"]
+pub struct CocktailCategory {
+    pub id: i32,
+    pub name: String,
+    #[graphql(description = "Categories are listed and rolled in ascending order")]
+    pub position: i32,
+}
+
+#[derive(AsChangeset, Debug, GraphQLInputObject, Insertable)]
+#[table_name = "cocktail_categories"]
+pub struct CocktailCategoryI {
+    pub name: String,
+    pub position: Option<i32>,
+}
+
+/// A bar with its own cocktails and prices.
+#[derive(Clone, Debug, Identifiable, Queryable)]
+pub struct Menu {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(AsChangeset, Debug, GraphQLInputObject, Insertable)]
+#[table_name = "menus"]
+pub struct MenuI {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, GraphQLObject, Identifiable, Queryable)]
 pub struct Ingredient {
     pub id: i32,
     pub name: String,
 }
 
+#[derive(AsChangeset, Debug, GraphQLInputObject, Insertable)]
+#[table_name = "ingredients"]
+pub struct IngredientI {
+    pub name: String,
+}
+
 #[derive(Debug, Identifiable, Queryable)]
 pub struct CocktailIngredient {
     pub id: i32,
@@ -197,6 +382,77 @@ pub struct CocktailIngredient {
     pub ingredient_id: i32,
 }
 
+#[derive(AsChangeset, Debug, Insertable)]
+#[table_name = "cocktail_ingredients"]
+pub struct CocktailIngredientI {
+    pub cocktail_id: i32,
+    pub ingredient_id: i32,
+}
+
+/// An ingredient the user doesn't want in their drinks.
+#[derive(Debug, Identifiable, Queryable)]
+pub struct UserExcludedIngredient {
+    pub id: i32,
+    pub user_id: i32,
+    pub ingredient_id: i32,
+}
+
+#[derive(AsChangeset, Debug, Insertable)]
+#[table_name = "user_excluded_ingredients"]
+pub struct UserExcludedIngredientI {
+    pub user_id: i32,
+    pub ingredient_id: i32,
+}
+
+impl ApiToken {
+    /// Sorted by `order_by`, ties are broken by the id.
+    pub fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[ApiTokenOrder]) -> DieselResult<Vec<ApiToken>> {
+        use schema::api_tokens::dsl;
+
+        let mut query = dsl::api_tokens.into_boxed();
+        for order in order_by {
+            query = match order.field {
+                ApiTokenOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                ApiTokenOrderField::NAME => then_order_by!(query, dsl::name, order.direction()),
+                ApiTokenOrderField::CREATED => then_order_by!(query, dsl::created, order.direction()),
+            };
+        }
+
+        query
+            .then_order_by(dsl::id)
+            .limit(constraints.limit as i64)
+            .offset(constraints.offset as i64)
+            .load::<ApiToken>(connection)
+    }
+
+    pub fn get_by_token_hash(token_hash: &str, connection: &diesel::PgConnection) -> DieselResult<Option<ApiToken>> {
+        schema::api_tokens::table
+            .filter(schema::api_tokens::dsl::token_hash.eq(token_hash))
+            .first::<ApiToken>(connection)
+            .optional()
+    }
+
+    pub fn delete(id: i32, connection: &diesel::PgConnection) -> DieselResult<ApiToken> {
+        diesel::delete(schema::api_tokens::table.find(id))
+            .get_result(connection)
+    }
+}
+
+impl ApiTokenI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<ApiToken> {
+        use schema::api_tokens::dsl::*;
+
+        diesel::insert_into(schema::api_tokens::table)
+            .values(self)
+            .on_conflict(token_hash)
+            .do_update()
+            .set(self)
+            .load::<ApiToken>(connection)?
+            .pop()
+            .ok_or(diesel::NotFound)
+    }
+}
+
 impl Chat {
     pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Chat> {
         schema::chats::table
@@ -204,11 +460,45 @@ impl Chat {
             .first(connection)
     }
 
+    pub fn get_by_telegram_id(telegram_id: i32, connection: &diesel::PgConnection) -> DieselResult<Option<Chat>> {
+        schema::chats::table
+            .filter(schema::chats::dsl::telegram_id.eq(telegram_id))
+            .first(connection)
+            .optional()
+    }
+
     pub fn get(limit: u32, connection: &diesel::PgConnection) -> DieselResult<Vec<Chat>> {
         schema::chats::table
             .limit(limit as i64)
             .load(connection)
     }
+
+    /// Unless `cascade` is set, the database refuses to delete a chat which still has members, events or messages.
+    pub fn delete(id: i32, cascade: bool, connection: &diesel::PgConnection) -> DieselResult<Chat> {
+        connection.transaction(|| {
+            if cascade {
+                diesel::delete(schema::chat_users::table
+                    .filter(schema::chat_users::dsl::chat_id.eq(id)))
+                    .execute(connection)?;
+                diesel::delete(schema::messages::table
+                    .filter(schema::messages::dsl::chat_id.eq(id)))
+                    .execute(connection)?;
+                let event_ids = schema::events::table
+                    .filter(schema::events::dsl::chat_id.eq(id))
+                    .select(schema::events::dsl::id)
+                    .load::<i32>(connection)?;
+                for event_id in event_ids {
+                    Event::delete(event_id, true, connection)?;
+                }
+                diesel::delete(schema::event_schedules::table
+                    .filter(schema::event_schedules::dsl::chat_id.eq(id)))
+                    .execute(connection)?;
+            }
+
+            diesel::delete(schema::chats::table.find(id))
+                .get_result(connection)
+        })
+    }
 }
 
 impl ChatI {
@@ -226,6 +516,40 @@ impl ChatI {
     }
 }
 
+impl ChatPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<Chat> {
+        diesel::update(schema::chats::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
+impl ChatUser {
+    pub fn get_by_chat_and_user(chat_id: i32, user_id: i32, connection: &diesel::PgConnection) -> DieselResult<Option<ChatUser>> {
+        schema::chat_users::table
+            .filter(schema::chat_users::dsl::chat_id.eq(chat_id))
+            .filter(schema::chat_users::dsl::user_id.eq(user_id))
+            .first::<ChatUser>(connection)
+            .optional()
+    }
+
+    pub fn is_admin(chat_id: i32, telegram_id: i32, connection: &diesel::PgConnection) -> DieselResult<bool> {
+        Ok(schema::chat_users::table
+            .inner_join(schema::users::table)
+            .filter(schema::chat_users::dsl::chat_id.eq(chat_id))
+            .filter(schema::users::dsl::telegram_id.eq(telegram_id))
+            .select(schema::chat_users::dsl::admin)
+            .first::<bool>(connection)
+            .optional()?
+            .unwrap_or(false))
+    }
+
+    pub fn delete(id: i32, connection: &diesel::PgConnection) -> DieselResult<ChatUser> {
+        diesel::delete(schema::chat_users::table.find(id))
+            .get_result(connection)
+    }
+}
+
 impl ChatUserI {
     pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<ChatUser> {
         use schema::chat_users::dsl::*;
@@ -241,6 +565,14 @@ impl ChatUserI {
     }
 }
 
+impl ChatUserPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<ChatUser> {
+        diesel::update(schema::chat_users::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
 impl Event {
     pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Event> {
         schema::events::table
@@ -250,37 +582,123 @@ impl Event {
             .ok_or(diesel::NotFound)
     }
 
+    pub fn get_active_by_chat_id(chat_id: i32, connection: &diesel::PgConnection) -> DieselResult<Option<Event>> {
+        schema::events::table
+            .filter(schema::events::dsl::chat_id.eq(chat_id))
+            .filter(schema::events::dsl::active.eq(true))
+            .first::<Event>(connection)
+            .optional()
+    }
+
+    /// Activates the event of `chat_id` at `timestamp`, creating it if necessary.
+    ///
+    /// The database refuses to open a second active event for the same chat.
+    pub fn open(chat_id: i32, timestamp: DateTime<Utc>, connection: &diesel::PgConnection) -> DieselResult<Event> {
+        connection.transaction(|| {
+            let event = EventI {
+                chat_id,
+                timestamp,
+                active: false,
+                menu_id: None,
+            }.insert(connection)?;
+
+            diesel::update(schema::events::table.find(event.id))
+                .set(schema::events::dsl::active.eq(true))
+                .get_result(connection)
+        })
+    }
+
+    pub fn close(id: i32, connection: &diesel::PgConnection) -> DieselResult<Event> {
+        diesel::update(schema::events::table.find(id))
+            .set(schema::events::dsl::active.eq(false))
+            .get_result(connection)
+    }
+
     pub(crate) fn into_model_event(&self, connection: &diesel::PgConnection) -> DieselResult<models::Event> {
-        let chat = Chat::get_by_id(self.chat_id, connection)?;
+        let chat: models::Chat = Chat::get_by_id(self.chat_id, connection)?.into();
 
         Ok(models::Event {
             id: self.id,
-            chat: chat.into(),
-            timestamp: self.timestamp,
+            timestamp: chat.localize(self.timestamp),
+            chat,
             active: self.active,
+            menu_id: self.menu_id,
+        })
+    }
+
+    /// Unless `cascade` is set, the database refuses to delete an event which still has users.
+    pub fn delete(id: i32, cascade: bool, connection: &diesel::PgConnection) -> DieselResult<Event> {
+        connection.transaction(|| {
+            if cascade {
+                let event_user_ids = schema::event_users::table
+                    .filter(schema::event_users::dsl::event_id.eq(id))
+                    .select(schema::event_users::dsl::id)
+                    .load::<i32>(connection)?;
+                for event_user_id in event_user_ids {
+                    EventUser::delete(event_user_id, true, connection)?;
+                }
+            }
+
+            diesel::delete(schema::events::table.find(id))
+                .get_result(connection)
         })
     }
 }
 
 impl EventI {
+    /// An existing event at the same time keeps its `active` state, use `Event::open` and `Event::close` to change it.
     pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<Event> {
+        use diesel::upsert::excluded;
         use schema::events::dsl::*;
 
         diesel::insert_into(schema::events::table)
             .values(self)
             .on_conflict((chat_id, timestamp))
             .do_update()
+            .set(timestamp.eq(excluded(timestamp)))
+            .get_result(connection)
+    }
+}
+
+impl EventPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<Event> {
+        diesel::update(schema::events::table.find(id))
             .set(self)
-            .execute(connection)
-            .map_err(|_| diesel::NotFound)?;
-        diesel::insert_into(schema::events::table)
+            .get_result(connection)
+    }
+}
+
+impl EventSchedule {
+    pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<EventSchedule> {
+        schema::event_schedules::table
+            .find(id)
+            .first(connection)
+    }
+
+    pub fn get_all(connection: &diesel::PgConnection) -> DieselResult<Vec<EventSchedule>> {
+        schema::event_schedules::table
+            .load(connection)
+    }
+
+    pub fn delete(id: i32, connection: &diesel::PgConnection) -> DieselResult<EventSchedule> {
+        diesel::delete(schema::event_schedules::table.find(id))
+            .get_result(connection)
+    }
+}
+
+impl EventScheduleI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<EventSchedule> {
+        diesel::insert_into(schema::event_schedules::table)
             .values(self)
-            .on_conflict((chat_id, active))
-            .do_update()
+            .get_result(connection)
+    }
+}
+
+impl EventSchedulePatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<EventSchedule> {
+        diesel::update(schema::event_schedules::table.find(id))
             .set(self)
-            .load::<Event>(connection)?
-            .pop()
-            .ok_or(diesel::NotFound)
+            .get_result(connection)
     }
 }
 
@@ -292,6 +710,20 @@ impl EventUser {
             .pop()
             .ok_or(diesel::NotFound)
     }
+
+    /// Unless `cascade` is set, the database refuses to delete an event user who already rolled.
+    pub fn delete(id: i32, cascade: bool, connection: &diesel::PgConnection) -> DieselResult<EventUser> {
+        connection.transaction(|| {
+            if cascade {
+                diesel::delete(schema::user_rolls::table
+                    .filter(schema::user_rolls::dsl::event_user_id.eq(id)))
+                    .execute(connection)?;
+            }
+
+            diesel::delete(schema::event_users::table.find(id))
+                .get_result(connection)
+        })
+    }
 }
 
 impl EventUserI {
@@ -309,6 +741,14 @@ impl EventUserI {
     }
 }
 
+impl EventUserPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<EventUser> {
+        diesel::update(schema::event_users::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
 impl MessageI {
     pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<Message> {
         use schema::messages::dsl::*;
@@ -339,6 +779,45 @@ impl UserI {
     }
 }
 
+impl UserPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<User> {
+        diesel::update(schema::users::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
+impl User {
+    /// Unless `cascade` is set, the database refuses to delete a user who is still referenced by chats or events.
+    /// Messages sent by the user are kept when cascading, they just lose their sender.
+    pub fn delete(id: i32, cascade: bool, connection: &diesel::PgConnection) -> DieselResult<User> {
+        connection.transaction(|| {
+            if cascade {
+                diesel::delete(schema::chat_users::table
+                    .filter(schema::chat_users::dsl::user_id.eq(id)))
+                    .execute(connection)?;
+                diesel::delete(schema::user_excluded_ingredients::table
+                    .filter(schema::user_excluded_ingredients::dsl::user_id.eq(id)))
+                    .execute(connection)?;
+                diesel::update(schema::messages::table
+                    .filter(schema::messages::dsl::user_id.eq(id)))
+                    .set(schema::messages::dsl::user_id.eq(None::<i32>))
+                    .execute(connection)?;
+                let event_user_ids = schema::event_users::table
+                    .filter(schema::event_users::dsl::user_id.eq(id))
+                    .select(schema::event_users::dsl::id)
+                    .load::<i32>(connection)?;
+                for event_user_id in event_user_ids {
+                    EventUser::delete(event_user_id, true, connection)?;
+                }
+            }
+
+            diesel::delete(schema::users::table.find(id))
+                .get_result(connection)
+        })
+    }
+}
+
 impl UserRoll {
     pub(crate) fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<UserRoll> {
         schema::user_rolls::table
@@ -347,6 +826,21 @@ impl UserRoll {
             .pop()
             .ok_or(diesel::NotFound)
     }
+
+    pub(crate) fn get_by_callback_query(event_user_id: i32, callback_query_id: &str, connection: &diesel::PgConnection) -> DieselResult<Option<UserRoll>> {
+        use schema::user_rolls::dsl;
+
+        dsl::user_rolls
+            .filter(dsl::event_user_id.eq(event_user_id))
+            .filter(dsl::callback_query_id.eq(callback_query_id))
+            .first::<UserRoll>(connection)
+            .optional()
+    }
+
+    pub fn delete(id: i32, connection: &diesel::PgConnection) -> DieselResult<UserRoll> {
+        diesel::delete(schema::user_rolls::table.find(id))
+            .get_result(connection)
+    }
 }
 
 impl UserRollI {
@@ -364,14 +858,81 @@ impl UserRollI {
     }
 }
 
+impl UserRollPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<UserRoll> {
+        diesel::update(schema::user_rolls::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
 impl Cocktail {
-    pub(crate) fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<Cocktail>> {
-        schema::cocktails::table
+    /// Retired cocktails are left out unless `include_retired` is set. Sorted by `order_by`, ties are broken by the id.
+    pub(crate) fn get(connection: &diesel::PgConnection, constraints: Constraints, filter: CocktailFilter, order_by: &[CocktailOrder], menu_id: Option<i32>, include_retired: bool) -> DieselResult<Vec<Cocktail>> {
+        use schema::cocktails::dsl;
+
+        let mut query = Cocktail::filtered(filter, menu_id, include_retired);
+        for order in order_by {
+            query = match order.field {
+                CocktailOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                CocktailOrderField::NAME => then_order_by!(query, dsl::name, order.direction()),
+                CocktailOrderField::CATEGORY => then_order_by!(query, dsl::category, order.direction()),
+                CocktailOrderField::PRICE => then_order_by!(query, dsl::price, order.direction()),
+            };
+        }
+
+        query
+            .then_order_by(dsl::id)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
             .load::<Cocktail>(connection)
     }
 
+    /// Number of cocktails `get` pages through with the same filters.
+    pub(crate) fn count(connection: &diesel::PgConnection, filter: CocktailFilter, menu_id: Option<i32>, include_retired: bool) -> DieselResult<i64> {
+        Cocktail::filtered(filter, menu_id, include_retired)
+            .count()
+            .get_result(connection)
+    }
+
+    fn filtered(filter: CocktailFilter, menu_id: Option<i32>, include_retired: bool) -> schema::cocktails::BoxedQuery<'static, Pg> {
+        use schema::cocktail_ingredients::dsl as cocktail_ingredients;
+        use schema::cocktails::dsl;
+
+        let mut query = dsl::cocktails.into_boxed();
+
+        if let Some(menu_id) = menu_id {
+            query = query.filter(dsl::menu_id.eq(menu_id));
+        }
+        if !include_retired {
+            query = query.filter(dsl::retired.eq(false));
+        }
+        if let Some(name) = filter.name {
+            query = query.filter(dsl::name.ilike(format!("%{}%", escape_like(&name))));
+        }
+        if let Some(category_id) = filter.category_id {
+            query = query.filter(dsl::category.eq(category_id));
+        }
+        if let Some(jumbo) = filter.jumbo {
+            query = query.filter(dsl::jumbo.eq(jumbo));
+        }
+        if let Some(alcoholic) = filter.alcoholic {
+            query = query.filter(dsl::alcoholic.eq(alcoholic));
+        }
+        for ingredient_id in filter.ingredient_ids.unwrap_or_default() {
+            query = query.filter(dsl::id.eq_any(cocktail_ingredients::cocktail_ingredients
+                .select(cocktail_ingredients::cocktail_id)
+                .filter(cocktail_ingredients::ingredient_id.eq(ingredient_id))));
+        }
+        if let Some(excluded_ingredient_ids) = filter.excluded_ingredient_ids {
+            query = query.filter(diesel::dsl::not(dsl::id.eq_any(cocktail_ingredients::cocktail_ingredients
+                .select(cocktail_ingredients::cocktail_id)
+                .filter(cocktail_ingredients::ingredient_id.eq(any(excluded_ingredient_ids))))));
+        }
+
+        query
+    }
+
     pub(crate) fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Cocktail> {
         schema::cocktails::table
             .filter(schema::cocktails::dsl::id.eq(id))
@@ -379,11 +940,163 @@ impl Cocktail {
             .pop()
             .ok_or(diesel::NotFound)
     }
+
+    /// Names are only unique within a menu.
+    pub(crate) fn get_by_name(menu_id: i32, name: &str, connection: &diesel::PgConnection) -> DieselResult<Option<Cocktail>> {
+        schema::cocktails::table
+            .filter(schema::cocktails::dsl::menu_id.eq(menu_id))
+            .filter(schema::cocktails::dsl::name.eq(name))
+            .first::<Cocktail>(connection)
+            .optional()
+    }
+
+    /// All cocktails matching the given flags, ordered by the position of their category so that
+    /// neighbouring dice values map to drinks of the same category. Retired cocktails and cocktails
+    /// containing an ingredient excluded by `user_id` are left out.
+    pub(crate) fn get_by_flags(menu_id: i32, user_id: i32, jumbo: bool, alcoholic: bool, connection: &diesel::PgConnection) -> DieselResult<Vec<Cocktail>> {
+        use schema::cocktail_ingredients::dsl as cocktail_ingredients;
+        use schema::cocktails::dsl;
+        use schema::user_excluded_ingredients::dsl as user_excluded_ingredients;
+
+        let excluded_cocktail_ids = cocktail_ingredients::cocktail_ingredients
+            .inner_join(schema::user_excluded_ingredients::table
+                .on(user_excluded_ingredients::ingredient_id.eq(cocktail_ingredients::ingredient_id)))
+            .filter(user_excluded_ingredients::user_id.eq(user_id))
+            .select(cocktail_ingredients::cocktail_id);
+
+        dsl::cocktails
+            .inner_join(schema::cocktail_categories::table)
+            .filter(dsl::menu_id.eq(menu_id))
+            .filter(dsl::jumbo.eq(jumbo))
+            .filter(dsl::alcoholic.eq(alcoholic))
+            .filter(dsl::retired.eq(false))
+            .filter(diesel::dsl::not(dsl::id.eq_any(excluded_cocktail_ids)))
+            .order((schema::cocktail_categories::dsl::position, dsl::category, dsl::id))
+            .select(schema::cocktails::all_columns)
+            .load::<Cocktail>(connection)
+    }
+}
+
+impl CocktailI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<Cocktail> {
+        diesel::insert_into(schema::cocktails::table)
+            .values(self)
+            .get_result(connection)
+    }
+}
+
+impl CocktailPatch {
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<Cocktail> {
+        diesel::update(schema::cocktails::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
+impl CocktailCategory {
+    pub(crate) fn get(connection: &diesel::PgConnection) -> DieselResult<Vec<CocktailCategory>> {
+        use schema::cocktail_categories::dsl;
+
+        dsl::cocktail_categories
+            .order((dsl::position, dsl::id))
+            .load::<CocktailCategory>(connection)
+    }
+
+    pub(crate) fn get_by_ids(ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, CocktailCategory>> {
+        Ok(schema::cocktail_categories::table
+            .filter(schema::cocktail_categories::dsl::id.eq(any(ids.to_vec())))
+            .load::<CocktailCategory>(connection)?
+            .into_iter()
+            .map(|category| (category.id, category))
+            .collect())
+    }
+
+    /// The database refuses to delete a category which is still used by cocktails.
+    pub fn delete(id: i32, connection: &diesel::PgConnection) -> DieselResult<CocktailCategory> {
+        diesel::delete(schema::cocktail_categories::table.find(id))
+            .get_result(connection)
+    }
+}
+
+impl CocktailCategoryI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<CocktailCategory> {
+        diesel::insert_into(schema::cocktail_categories::table)
+            .values(self)
+            .get_result(connection)
+    }
+
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<CocktailCategory> {
+        diesel::update(schema::cocktail_categories::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
+}
+
+impl Menu {
+    /// Sorted by `order_by`, ties are broken by the id.
+    pub(crate) fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[MenuOrder]) -> DieselResult<Vec<Menu>> {
+        use schema::menus::dsl;
+
+        let mut query = dsl::menus.into_boxed();
+        for order in order_by {
+            query = match order.field {
+                MenuOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                MenuOrderField::NAME => then_order_by!(query, dsl::name, order.direction()),
+            };
+        }
+
+        query
+            .then_order_by(dsl::id)
+            .limit(constraints.limit as i64)
+            .offset(constraints.offset as i64)
+            .load::<Menu>(connection)
+    }
+
+    pub(crate) fn get_by_ids(ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Menu>> {
+        Ok(schema::menus::table
+            .filter(schema::menus::dsl::id.eq(any(ids.to_vec())))
+            .load::<Menu>(connection)?
+            .into_iter()
+            .map(|menu| (menu.id, menu))
+            .collect())
+    }
+
+    /// The database refuses to delete a menu which is still used by cocktails, chats or events.
+    pub fn delete(id: i32, connection: &diesel::PgConnection) -> DieselResult<Menu> {
+        diesel::delete(schema::menus::table.find(id))
+            .get_result(connection)
+    }
+}
+
+impl MenuI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<Menu> {
+        diesel::insert_into(schema::menus::table)
+            .values(self)
+            .get_result(connection)
+    }
+
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<Menu> {
+        diesel::update(schema::menus::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
 }
 
 impl Ingredient {
-    pub(crate) fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<Ingredient>> {
-        schema::ingredients::table
+    /// Sorted by `order_by`, ties are broken by the id.
+    pub(crate) fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[IngredientOrder]) -> DieselResult<Vec<Ingredient>> {
+        use schema::ingredients::dsl;
+
+        let mut query = dsl::ingredients.into_boxed();
+        for order in order_by {
+            query = match order.field {
+                IngredientOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                IngredientOrderField::NAME => then_order_by!(query, dsl::name, order.direction()),
+            };
+        }
+
+        query
+            .then_order_by(dsl::id)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
             .load::<Ingredient>(connection)
@@ -396,6 +1109,37 @@ impl Ingredient {
             .pop()
             .ok_or(diesel::NotFound)
     }
+
+    /// Unless `cascade` is set, the database refuses to delete an ingredient which is still used by cocktails.
+    pub fn delete(id: i32, cascade: bool, connection: &diesel::PgConnection) -> DieselResult<Ingredient> {
+        connection.transaction(|| {
+            if cascade {
+                diesel::delete(schema::cocktail_ingredients::table
+                    .filter(schema::cocktail_ingredients::dsl::ingredient_id.eq(id)))
+                    .execute(connection)?;
+                diesel::delete(schema::user_excluded_ingredients::table
+                    .filter(schema::user_excluded_ingredients::dsl::ingredient_id.eq(id)))
+                    .execute(connection)?;
+            }
+
+            diesel::delete(schema::ingredients::table.find(id))
+                .get_result(connection)
+        })
+    }
+}
+
+impl IngredientI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<Ingredient> {
+        diesel::insert_into(schema::ingredients::table)
+            .values(self)
+            .get_result(connection)
+    }
+
+    pub fn update(&self, id: i32, connection: &diesel::PgConnection) -> DieselResult<Ingredient> {
+        diesel::update(schema::ingredients::table.find(id))
+            .set(self)
+            .get_result(connection)
+    }
 }
 
 
@@ -414,4 +1158,73 @@ impl CocktailIngredient {
             .pop()
             .ok_or(diesel::NotFound)
     }
+
+    pub fn delete(cocktail_id: i32, ingredient_id: i32, connection: &diesel::PgConnection) -> DieselResult<CocktailIngredient> {
+        use schema::cocktail_ingredients::dsl;
+
+        diesel::delete(dsl::cocktail_ingredients
+            .filter(dsl::cocktail_id.eq(cocktail_id))
+            .filter(dsl::ingredient_id.eq(ingredient_id)))
+            .get_result(connection)
+    }
+}
+
+impl UserExcludedIngredient {
+    /// Excluded ingredients keyed by their user id, ordered by name.
+    pub(crate) fn get_by_user_ids(user_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<Ingredient>>> {
+        let mut ingredients: HashMap<i32, Vec<Ingredient>> = HashMap::new();
+        for (user_id, ingredient) in schema::user_excluded_ingredients::table
+            .inner_join(schema::ingredients::table)
+            .filter(schema::user_excluded_ingredients::dsl::user_id.eq(any(user_ids.to_vec())))
+            .order(schema::ingredients::dsl::name)
+            .select((schema::user_excluded_ingredients::dsl::user_id, schema::ingredients::all_columns))
+            .load::<(i32, Ingredient)>(connection)? {
+            ingredients.entry(user_id).or_default().push(ingredient);
+        }
+
+        Ok(ingredients)
+    }
+
+    pub fn delete(user_id: i32, ingredient_id: i32, connection: &diesel::PgConnection) -> DieselResult<UserExcludedIngredient> {
+        use schema::user_excluded_ingredients::dsl;
+
+        diesel::delete(dsl::user_excluded_ingredients
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::ingredient_id.eq(ingredient_id)))
+            .get_result(connection)
+    }
+}
+
+impl UserExcludedIngredientI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<UserExcludedIngredient> {
+        use schema::user_excluded_ingredients::dsl::*;
+
+        diesel::insert_into(schema::user_excluded_ingredients::table)
+            .values(self)
+            .on_conflict((user_id, ingredient_id))
+            .do_update()
+            .set(self)
+            .get_result(connection)
+    }
+}
+
+impl CocktailIngredientI {
+    pub fn insert(&self, connection: &diesel::PgConnection) -> DieselResult<CocktailIngredient> {
+        use schema::cocktail_ingredients::dsl::*;
+
+        diesel::insert_into(schema::cocktail_ingredients::table)
+            .values(self)
+            .on_conflict((cocktail_id, ingredient_id))
+            .do_update()
+            .set(self)
+            .get_result(connection)
+    }
+}
+
+/// Escapes the wildcards of a `LIKE` pattern, so `input` only matches literally.
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
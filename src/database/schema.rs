@@ -1,3 +1,13 @@
+table! {
+    api_tokens (id) {
+        id -> Int4,
+        name -> Text,
+        token_hash -> Text,
+        admin -> Bool,
+        created -> Timestamptz,
+    }
+}
+
 table! {
     chat_users (id) {
         id -> Int4,
@@ -18,6 +28,17 @@ table! {
         description -> Nullable<Text>,
         current_keyboard -> Int2,
         spam_detection -> Bool,
+        timezone -> Text,
+        bill_rule -> Int2,
+        menu_id -> Int4,
+    }
+}
+
+table! {
+    cocktail_categories (id) {
+        id -> Int4,
+        name -> Text,
+        position -> Int4,
     }
 }
 
@@ -35,7 +56,11 @@ table! {
         name -> Varchar,
         jumbo -> Bool,
         alcoholic -> Bool,
-        category -> Int2,
+        category -> Int4,
+        price -> Nullable<Int4>,
+        jumbo_price -> Nullable<Int4>,
+        retired -> Bool,
+        menu_id -> Int4,
     }
 }
 
@@ -48,12 +73,23 @@ table! {
     }
 }
 
+table! {
+    event_schedules (id) {
+        id -> Int4,
+        chat_id -> Int4,
+        weekday -> Int2,
+        time -> Time,
+        timezone -> Text,
+    }
+}
+
 table! {
     events (id) {
         id -> Int4,
         chat_id -> Int4,
-        timestamp -> Timestamp,
+        timestamp -> Timestamptz,
         active -> Bool,
+        menu_id -> Nullable<Int4>,
     }
 }
 
@@ -64,15 +100,22 @@ table! {
     }
 }
 
+table! {
+    menus (id) {
+        id -> Int4,
+        name -> Text,
+    }
+}
+
 table! {
     messages (id) {
         id -> Int4,
         telegram_id -> Int4,
         chat_id -> Int4,
         user_id -> Nullable<Int4>,
-        timestamp -> Timestamp,
+        timestamp -> Timestamptz,
         reply_to_message_id -> Nullable<Int4>,
-        edit_timestamp -> Nullable<Timestamp>,
+        edit_timestamp -> Nullable<Timestamptz>,
         text -> Nullable<Text>,
         caption -> Nullable<Text>,
         new_chat_member_ids -> Nullable<Array<Int4>>,
@@ -86,6 +129,14 @@ table! {
     }
 }
 
+table! {
+    user_excluded_ingredients (id) {
+        id -> Int4,
+        user_id -> Int4,
+        ingredient_id -> Int4,
+    }
+}
+
 table! {
     user_rolls (id) {
         id -> Int4,
@@ -107,29 +158,42 @@ table! {
         telegram_id -> Int4,
         is_bot -> Bool,
         has_private_conversation -> Bool,
+        non_alcoholic -> Bool,
     }
 }
 
 joinable!(chat_users -> chats (chat_id));
 joinable!(chat_users -> users (user_id));
+joinable!(chats -> menus (menu_id));
 joinable!(cocktail_ingredients -> cocktails (cocktail_id));
 joinable!(cocktail_ingredients -> ingredients (ingredient_id));
+joinable!(cocktails -> cocktail_categories (category));
+joinable!(cocktails -> menus (menu_id));
+joinable!(event_schedules -> chats (chat_id));
 joinable!(event_users -> events (event_id));
 joinable!(event_users -> users (user_id));
 joinable!(events -> chats (chat_id));
+joinable!(events -> menus (menu_id));
 joinable!(messages -> chats (chat_id));
 joinable!(messages -> users (user_id));
+joinable!(user_excluded_ingredients -> ingredients (ingredient_id));
+joinable!(user_excluded_ingredients -> users (user_id));
 joinable!(user_rolls -> event_users (event_user_id));
 
 allow_tables_to_appear_in_same_query!(
+    api_tokens,
     chat_users,
     chats,
+    cocktail_categories,
     cocktail_ingredients,
     cocktails,
+    event_schedules,
     event_users,
     events,
     ingredients,
+    menus,
     messages,
+    user_excluded_ingredients,
     user_rolls,
     users,
 );
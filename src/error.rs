@@ -0,0 +1,166 @@
+use std::fmt;
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use juniper::{FieldError, IntoFieldError, Object, Value};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors surfaced to API clients, each carrying a machine readable code in `extensions.code`.
+#[derive(Debug)]
+pub enum Error {
+    NotFound,
+    /// The request clashes with the current state, e.g. a duplicate or a row which is still referenced.
+    Conflict { code: &'static str, message: String },
+    /// The caller lacks the permission, or is not authenticated at all.
+    Forbidden { code: &'static str, message: String },
+    /// The input is malformed or out of range.
+    Validation { code: &'static str, message: String },
+    /// Several fields of an input are invalid, all of them are reported at once.
+    InvalidInput(Vec<Violation>),
+    /// Anything unexpected, the details are only logged and never sent to the client.
+    Internal(String),
+}
+
+impl Error {
+    pub fn conflict<M: Into<String>>(code: &'static str, message: M) -> Self {
+        Error::Conflict { code, message: message.into() }
+    }
+
+    pub fn forbidden<M: Into<String>>(code: &'static str, message: M) -> Self {
+        Error::Forbidden { code, message: message.into() }
+    }
+
+    pub fn validation<M: Into<String>>(code: &'static str, message: M) -> Self {
+        Error::Validation { code, message: message.into() }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound => "NOT_FOUND",
+            Error::Conflict { code, .. } | Error::Forbidden { code, .. } | Error::Validation { code, .. } => *code,
+            Error::InvalidInput(_) => "INVALID_INPUT",
+            Error::Internal(_) => "INTERNAL",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "Not found"),
+            Error::Conflict { message, .. } | Error::Forbidden { message, .. } | Error::Validation { message, .. } => write!(f, "{}", message),
+            Error::InvalidInput(violations) => {
+                let messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+                write!(f, "Invalid input: {}", messages.join(", "))
+            }
+            Error::Internal(_) => write!(f, "Internal server error"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl IntoFieldError for Error {
+    fn into_field_error(self) -> FieldError {
+        if let Error::Internal(details) = &self {
+            error!("Internal error: {}", details);
+        }
+
+        let mut extensions = Object::with_capacity(2);
+        extensions.add_field("code", Value::scalar(self.code().to_string()));
+        if let Error::InvalidInput(violations) = &self {
+            extensions.add_field("fields", Value::list(violations
+                .iter()
+                .map(|violation| {
+                    let mut field = Object::with_capacity(2);
+                    field.add_field("field", Value::scalar(violation.field.to_string()));
+                    field.add_field("message", Value::scalar(violation.message.clone()));
+
+                    Value::object(field)
+                })
+                .collect()));
+        }
+
+        FieldError::new(self.to_string(), Value::object(extensions))
+    }
+}
+
+/// A single invalid field of an input, `field` is named as in the GraphQL schema.
+#[derive(Debug)]
+pub struct Violation {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` {}", self.field, self.message)
+    }
+}
+
+/// Collects the invalid fields of an input, so they can be reported together.
+#[derive(Debug, Default)]
+pub struct Violations(Vec<Violation>);
+
+impl Violations {
+    pub fn new() -> Self {
+        Violations::default()
+    }
+
+    /// Records `message` for `field` unless `valid` holds.
+    pub fn check<M: Into<String>>(&mut self, valid: bool, field: &'static str, message: M) {
+        if !valid {
+            self.0.push(Violation { field, message: message.into() });
+        }
+    }
+
+    pub fn into_result(self) -> Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidInput(self.0))
+        }
+    }
+}
+
+impl From<DieselError> for Error {
+    fn from(error: DieselError) -> Self {
+        match error {
+            DieselError::NotFound => Error::NotFound,
+            DieselError::DatabaseError(kind, info) => {
+                if let Some(error) = info.constraint_name().and_then(from_constraint) {
+                    return error;
+                }
+
+                match kind {
+                    DatabaseErrorKind::UniqueViolation => Error::conflict("ALREADY_EXISTS", "An entry with these values already exists"),
+                    // Postgres words violations by deletes and by inserts differently
+                    DatabaseErrorKind::ForeignKeyViolation if info.message().starts_with("update or delete") =>
+                        Error::conflict("STILL_REFERENCED", "The entry is still referenced by others"),
+                    DatabaseErrorKind::ForeignKeyViolation => Error::validation("INVALID_REFERENCE", "A referenced entry doesn't exist"),
+                    _ => match info.constraint_name() {
+                        // Unnamed check constraints are called `<table>_<column>_check`
+                        Some(constraint) if constraint.ends_with("_check") => Error::validation("INVALID_VALUE", format!("The value violates `{}`", constraint)),
+                        _ => Error::Internal(info.message().to_string()),
+                    },
+                }
+            }
+            error => Error::Internal(error.to_string()),
+        }
+    }
+}
+
+/// Constraints with a meaning of their own to clients.
+fn from_constraint(constraint: &str) -> Option<Error> {
+    Some(match constraint {
+        "valid_roll" => Error::validation("INVALID_ROLL", "Rolls have to be between 1 and 6"),
+        "user_rolls_event_user_id_callback_query_id_key" => Error::conflict("DUPLICATE_ROLL", "The callback query has already been rolled for"),
+        "events_chat_id_active_idx" => Error::conflict("EVENT_ALREADY_ACTIVE", "The chat already has an active event"),
+        "events_chat_id_timestamp_key" => Error::conflict("DUPLICATE_EVENT", "The chat already has an event at this time"),
+        "event_users_event_id_user_id_key" => Error::conflict("ALREADY_INVITED", "The user is already part of the event"),
+        "chat_users_chat_id_user_id_key" => Error::conflict("ALREADY_MEMBER", "The user is already part of the chat"),
+        "admin_cant_be_muted" => Error::validation("ADMIN_CANT_BE_MUTED", "Admins of a chat can't be muted"),
+        "event_schedules_chat_id_weekday_time_key" => Error::conflict("DUPLICATE_SCHEDULE", "The chat already has a schedule at this time"),
+        _ => return None,
+    })
+}
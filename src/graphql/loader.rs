@@ -0,0 +1,178 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::database::models::{Ingredient, Menu, UserExcludedIngredient};
+use crate::models::{Chat, ChatUser, Cocktail, CocktailCategory, CocktailIngredient, Event, EventSchedule, EventUser, Message, User, UserRoll};
+
+type DieselResult<T> = Result<T, diesel::result::Error>;
+
+/// Fetches the values for all given keys with a single query, keys without a value are left out.
+pub type BatchFn<K, V> = fn(&[K], &diesel::PgConnection) -> DieselResult<HashMap<K, V>>;
+
+/// Per-request cache which coalesces lookups into a single query.
+///
+/// Resolvers returning lists `register` the keys their children are going to ask for,
+/// all registered keys are then fetched together on the next cache miss.
+pub struct Loader<K, V> {
+    batch: BatchFn<K, V>,
+    pending: RefCell<HashSet<K>>,
+    cache: RefCell<HashMap<K, Option<V>>>,
+}
+
+impl<K, V> Loader<K, V>
+    where K: Clone + Eq + Hash,
+          V: Clone {
+    pub fn new(batch: BatchFn<K, V>) -> Self {
+        Loader {
+            batch,
+            pending: RefCell::new(HashSet::new()),
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn register<I: IntoIterator<Item=K>>(&self, keys: I) {
+        let cache = self.cache.borrow();
+
+        self.pending
+            .borrow_mut()
+            .extend(keys.into_iter().filter(|key| !cache.contains_key(key)));
+    }
+
+    pub fn load(&self, key: K, connection: &diesel::PgConnection) -> DieselResult<Option<V>> {
+        Ok(self.load_many(&[key], connection)?
+            .pop()
+            .and_then(|value| value))
+    }
+
+    pub fn load_many(&self, keys: &[K], connection: &diesel::PgConnection) -> DieselResult<Vec<Option<V>>> {
+        self.register(keys.iter().cloned());
+        self.fetch_pending(connection)?;
+
+        let cache = self.cache.borrow();
+        Ok(keys
+            .iter()
+            .map(|key| cache.get(key).and_then(|value| value.clone()))
+            .collect())
+    }
+
+    fn fetch_pending(&self, connection: &diesel::PgConnection) -> DieselResult<()> {
+        let keys: Vec<K> = self.pending.borrow_mut().drain().collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let mut values = (self.batch)(&keys, connection)?;
+        let mut cache = self.cache.borrow_mut();
+        for key in keys {
+            let value = values.remove(&key);
+            cache.insert(key, value);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Loaders {
+    pub users: Loader<i32, User>,
+    pub chats: Loader<i32, Chat>,
+    pub menus: Loader<i32, Menu>,
+    pub cocktail_categories: Loader<i32, CocktailCategory>,
+    /// Messages keyed by their chat id and telegram id
+    pub messages: Loader<(i32, i32), Message>,
+    /// Events keyed by their chat id
+    pub chat_events: Loader<i32, Vec<Event>>,
+    /// Members keyed by their chat id
+    pub chat_members: Loader<i32, Vec<ChatUser>>,
+    /// Schedules keyed by their chat id
+    pub chat_schedules: Loader<i32, Vec<EventSchedule>>,
+    /// Memberships keyed by their user id
+    pub user_chats: Loader<i32, Vec<ChatUser>>,
+    /// Invitations keyed by their user id
+    pub user_events: Loader<i32, Vec<EventUser>>,
+    /// Excluded ingredients keyed by their user id
+    pub user_excluded_ingredients: Loader<i32, Vec<Ingredient>>,
+    /// Invitations keyed by their event id
+    pub event_attendees: Loader<i32, Vec<EventUser>>,
+    /// Rolls keyed by their event id
+    pub event_rolls: Loader<i32, Vec<UserRoll>>,
+    /// Replies keyed by the chat id and telegram id of the message they reply to
+    pub message_replies: Loader<(i32, i32), Vec<Message>>,
+    /// Ingredients keyed by their cocktail id
+    pub cocktail_ingredients: Loader<i32, Vec<Ingredient>>,
+    /// Cocktails keyed by their menu id and name, as referred to by rolls
+    pub(crate) cocktails_by_name: Loader<(i32, String), Cocktail>,
+}
+
+impl Loaders {
+    pub fn new() -> Self {
+        Loaders {
+            users: Loader::new(User::get_by_ids),
+            chats: Loader::new(Chat::get_by_ids),
+            menus: Loader::new(Menu::get_by_ids),
+            cocktail_categories: Loader::new(CocktailCategory::get_by_ids),
+            messages: Loader::new(Message::get_by_telegram_ids),
+            chat_events: Loader::new(Event::get_by_chat_ids),
+            chat_members: Loader::new(ChatUser::get_by_chat_ids),
+            chat_schedules: Loader::new(EventSchedule::get_by_chat_ids),
+            user_chats: Loader::new(ChatUser::get_by_user_ids),
+            user_events: Loader::new(EventUser::get_by_user_ids),
+            user_excluded_ingredients: Loader::new(UserExcludedIngredient::get_by_user_ids),
+            event_attendees: Loader::new(EventUser::get_by_event_ids),
+            event_rolls: Loader::new(UserRoll::get_by_event_ids),
+            message_replies: Loader::new(Message::get_replies),
+            cocktail_ingredients: Loader::new(CocktailIngredient::get_by_cocktails),
+            cocktails_by_name: Loader::new(Cocktail::get_by_names),
+        }
+    }
+
+    /// Registers the relations of `chats`.
+    pub(crate) fn register_chats(&self, chats: &[Chat]) {
+        self.chat_events.register(chats.iter().map(|chat| chat.id));
+        self.chat_members.register(chats.iter().map(|chat| chat.id));
+        self.chat_schedules.register(chats.iter().map(|chat| chat.id));
+    }
+
+    /// Registers the relations of `users`.
+    pub(crate) fn register_users(&self, users: &[User]) {
+        self.user_chats.register(users.iter().map(|user| user.id));
+        self.user_events.register(users.iter().map(|user| user.id));
+        self.user_excluded_ingredients.register(users.iter().map(|user| user.id));
+    }
+
+    /// Registers the relations of `events`.
+    pub(crate) fn register_events(&self, events: &[Event]) {
+        self.event_attendees.register(events.iter().map(|event| event.id));
+        self.event_rolls.register(events.iter().map(|event| event.id));
+    }
+
+    /// Registers the cocktails `rolls` refer to.
+    pub(crate) fn register_rolls(&self, rolls: &[UserRoll]) {
+        self.cocktails_by_name.register(rolls
+            .iter()
+            .map(|user_roll| (user_roll.event_user.event.effective_menu_id(), user_roll.drink.clone())));
+    }
+
+    /// Registers all users, chats and messages referenced by `messages`.
+    pub(crate) fn register_messages(&self, messages: &[Message]) {
+        self.users.register(messages
+            .iter()
+            .flat_map(|message| message.new_chat_member_ids.iter().cloned().chain(message.left_chat_member_id)));
+        self.chats.register(messages
+            .iter()
+            .flat_map(|message| message.migrate_to_chat_id.into_iter().chain(message.migrate_from_chat_id)));
+        self.messages.register(messages
+            .iter()
+            .flat_map(|message| message.reply_to_message_id
+                .into_iter()
+                .chain(message.pinned_message_id)
+                .map(move |telegram_id| (message.chat.id, telegram_id))));
+        self.message_replies.register(messages.iter().map(|message| (message.chat.id, message.telegram_id)));
+    }
+}
+
+impl Default for Loaders {
+    fn default() -> Self {
+        Loaders::new()
+    }
+}
@@ -1,9 +1,93 @@
+use crate::auth::{AuthError, Credentials, Principal};
+use crate::database::models::{ApiToken, ChatUser};
 use crate::db::PrimaryDb;
+use crate::error::{Error, Result};
+use crate::graphql::loader::Loaders;
 
+pub mod loader;
+pub mod order;
+pub mod pagination;
 pub mod schema;
+pub mod validation;
 
 pub struct Context {
-    pub connection: PrimaryDb
+    pub connection: PrimaryDb,
+    pub principal: Principal,
+    pub loaders: Loaders,
+}
+
+impl Context {
+    /// Resolves the principal with the connection of the request.
+    pub fn new(connection: PrimaryDb, credentials: Credentials) -> std::result::Result<Self, AuthError> {
+        let principal = credentials.resolve(&connection.0)?;
+
+        Ok(Context {
+            connection,
+            principal,
+            loaders: Loaders::new(),
+        })
+    }
+
+    /// Fails unless the request was made with a valid API token or by a logged in user.
+    pub fn authenticated(&self) -> Result<&Principal> {
+        match &self.principal {
+            Principal::Anonymous => Err(Error::forbidden("UNAUTHENTICATED", "Authentication required")),
+            principal => Ok(principal),
+        }
+    }
+
+    /// Fails unless the request was made with a valid API token, logged in users are refused.
+    pub fn token(&self) -> Result<&ApiToken> {
+        match self.authenticated()? {
+            Principal::Token { api_token, .. } => Ok(api_token),
+            _ => Err(Error::forbidden("FORBIDDEN", "API token required")),
+        }
+    }
+
+    /// Fails unless the request was made with a valid API token or by the logged in user `user_id` themselves.
+    pub fn token_or_self(&self, user_id: i32) -> Result<()> {
+        match self.authenticated()? {
+            Principal::Token { .. } => Ok(()),
+            Principal::User(user) if user.id == user_id => Ok(()),
+            _ => Err(Error::forbidden("FORBIDDEN", "Logged in users are only allowed to change their own data")),
+        }
+    }
+
+    /// Fails unless the request was made with a valid admin API token.
+    pub fn admin(&self) -> Result<&ApiToken> {
+        match self.authenticated()? {
+            Principal::Token { api_token, .. } if api_token.admin => Ok(api_token),
+            _ => Err(Error::forbidden("FORBIDDEN", "Admin token required")),
+        }
+    }
+
+    /// Telegram id of the user the request is made by or on behalf of.
+    pub fn telegram_id(&self) -> Option<i32> {
+        match &self.principal {
+            Principal::Token { telegram_id, .. } => *telegram_id,
+            Principal::User(user) => Some(user.telegram_id),
+            Principal::Anonymous => None,
+        }
+    }
+
+    /// Fails unless the request is made on behalf of an admin of the chat, admin tokens are always allowed.
+    pub fn chat_admin(&self, chat_id: i32) -> Result<()> {
+        self.authenticated()?;
+        if self.admin().is_ok() {
+            return Ok(());
+        }
+
+        let is_chat_admin = match self.telegram_id() {
+            Some(telegram_id) => ChatUser::is_admin(chat_id, telegram_id, &self.connection.0)?,
+            None => false,
+        };
+
+        if is_chat_admin {
+            Ok(())
+        } else {
+            Err(Error::forbidden("FORBIDDEN", "Only admins of the chat are allowed to do this"))
+        }
+    }
 }
 
 impl juniper::Context for Context {}
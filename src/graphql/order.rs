@@ -0,0 +1,55 @@
+#[derive(Clone, Copy, Debug, GraphQLEnum)]
+pub enum SortDirection {
+    ASC,
+    DESC,
+}
+
+impl Default for SortDirection {
+    fn default() -> Self {
+        SortDirection::ASC
+    }
+}
+
+/// Appends `$column` in `$direction` to the `ORDER BY` clause of a boxed query.
+#[macro_export]
+macro_rules! then_order_by {
+    ($query:expr, $column:expr, $direction:expr) => {
+        match $direction {
+            $crate::graphql::order::SortDirection::ASC => $query.then_order_by($column.asc()),
+            $crate::graphql::order::SortDirection::DESC => $query.then_order_by($column.desc()),
+        }
+    };
+}
+
+/// Declares the `orderBy` input of a list, the variants of `$field` are the only columns it can be sorted by.
+macro_rules! order_input {
+    ($input:ident, $field:ident { $($variant:ident),+ $(,)? }) => {
+        #[derive(Clone, Copy, Debug, GraphQLEnum)]
+        pub enum $field {
+            $($variant),+
+        }
+
+        #[derive(Clone, Copy, Debug, GraphQLInputObject)]
+        pub struct $input {
+            pub(crate) field: $field,
+            #[graphql(description = "Ascending unless given")]
+            pub(crate) direction: Option<SortDirection>,
+        }
+
+        impl $input {
+            pub(crate) fn direction(&self) -> SortDirection {
+                self.direction.unwrap_or_default()
+            }
+        }
+    };
+}
+
+order_input!(ApiTokenOrder, ApiTokenOrderField { ID, NAME, CREATED });
+order_input!(ChatOrder, ChatOrderField { ID, TITLE, TELEGRAM_ID });
+order_input!(CocktailOrder, CocktailOrderField { ID, NAME, CATEGORY, PRICE });
+order_input!(EventOrder, EventOrderField { ID, TIMESTAMP });
+order_input!(IngredientOrder, IngredientOrderField { ID, NAME });
+order_input!(MenuOrder, MenuOrderField { ID, NAME });
+order_input!(MessageOrder, MessageOrderField { ID, TIMESTAMP });
+order_input!(UserOrder, UserOrderField { ID, USERNAME, FIRST_NAME, TELEGRAM_ID });
+order_input!(UserRollOrder, UserRollOrderField { ID, ROLL, DRINK });
@@ -0,0 +1,242 @@
+use std::cmp::{max, min};
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+use crate::graphql::Context;
+use crate::graphql::schema::Constraints;
+use crate::models::{Chat, Cocktail, Event, Message, User, UserRoll};
+
+/// Page size used unless `first` or `last` is given.
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Cursors are the hex encoded position of a node within the ordered results, clients must treat them as opaque.
+fn encode_cursor(offset: i64) -> String {
+    hex::encode(format!("offset:{}", offset))
+}
+
+fn invalid_cursor() -> Error {
+    Error::validation("INVALID_CURSOR", "The cursor is invalid")
+}
+
+/// Only positions within `0..=total` are accepted, anything else can't have been handed out.
+fn decode_cursor(cursor: &str, total: i64) -> Result<i64> {
+    let decoded = String::from_utf8(hex::decode(cursor).map_err(|_| invalid_cursor())?).map_err(|_| invalid_cursor())?;
+    if !decoded.starts_with("offset:") {
+        return Err(invalid_cursor());
+    }
+
+    match decoded["offset:".len()..].parse() {
+        Ok(offset) if offset >= 0 && offset <= total => Ok(offset),
+        _ => Err(invalid_cursor()),
+    }
+}
+
+#[derive(Debug, GraphQLObject)]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub has_previous_page: bool,
+    pub start_cursor: Option<String>,
+    pub end_cursor: Option<String>,
+}
+
+/// The slice `start..end` of `total` results selected by the Relay paging arguments.
+#[derive(Clone, Copy, Debug)]
+pub struct Page {
+    start: i64,
+    end: i64,
+    total: i64,
+}
+
+impl Page {
+    /// `after` and `before` narrow the results first, then `first` keeps the leading and `last` the trailing nodes.
+    pub fn new(first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, total: i64) -> Result<Page> {
+        if first.map_or(false, |first| first < 0) || last.map_or(false, |last| last < 0) {
+            return Err(Error::validation("INVALID_PAGE_SIZE", "`first` and `last` must not be negative"));
+        }
+
+        let mut start = 0;
+        let mut end = total;
+        if let Some(after) = after {
+            start = max(start, decode_cursor(&after, total)?.checked_add(1).ok_or_else(invalid_cursor)?);
+        }
+        if let Some(before) = before {
+            end = min(end, decode_cursor(&before, total)?);
+        }
+        end = max(start, end);
+
+        match (first, last) {
+            (None, None) => end = min(end, start + DEFAULT_PAGE_SIZE),
+            (first, last) => {
+                if let Some(first) = first {
+                    end = min(end, start + first as i64);
+                }
+                if let Some(last) = last {
+                    start = max(start, end - last as i64);
+                }
+            }
+        }
+
+        Ok(Page { start, end, total })
+    }
+
+    /// `limit` and `offset` to load the nodes of the page with.
+    pub fn constraints(&self) -> Result<Constraints> {
+        let out_of_range = |_| Error::validation("INVALID_PAGE", "The page is out of range");
+
+        Ok(Constraints {
+            limit: i32::try_from(self.end - self.start).map_err(out_of_range)?,
+            offset: i32::try_from(self.start).map_err(out_of_range)?,
+        })
+    }
+
+    fn cursors(&self, count: usize) -> Vec<String> {
+        (self.start..self.start + count as i64).map(encode_cursor).collect()
+    }
+
+    fn page_info(&self, cursors: &[String]) -> PageInfo {
+        PageInfo {
+            has_next_page: self.start + cursors.len() as i64 < self.total,
+            has_previous_page: self.start > 0,
+            start_cursor: cursors.first().cloned(),
+            end_cursor: cursors.last().cloned(),
+        }
+    }
+}
+
+/// Declares a Relay connection and its edge type for `$node`.
+macro_rules! connection {
+    ($connection:ident, $edge:ident, $node:ty) => {
+        #[derive(GraphQLObject)]
+        #[graphql(Context = Context)]
+        pub(crate) struct $edge {
+            pub(crate) cursor: String,
+            pub(crate) node: $node,
+        }
+
+        #[derive(GraphQLObject)]
+        #[graphql(Context = Context)]
+        pub(crate) struct $connection {
+            pub(crate) edges: Vec<$edge>,
+            pub(crate) page_info: PageInfo,
+            pub(crate) total_count: i32,
+        }
+
+        impl $connection {
+            /// `nodes` have to be loaded with the constraints of `page`.
+            pub(crate) fn new(nodes: Vec<$node>, page: Page) -> Result<Self> {
+                let cursors = page.cursors(nodes.len());
+                let total_count = i32::try_from(page.total)
+                    .map_err(|_| Error::Internal(format!("{} results exceed the range of `totalCount`", page.total)))?;
+
+                Ok($connection {
+                    page_info: page.page_info(&cursors),
+                    total_count,
+                    edges: cursors
+                        .into_iter()
+                        .zip(nodes)
+                        .map(|(cursor, node)| $edge { cursor, node })
+                        .collect(),
+                })
+            }
+        }
+    };
+}
+
+connection!(ChatConnection, ChatEdge, Chat);
+connection!(UserConnection, UserEdge, User);
+connection!(EventConnection, EventEdge, Event);
+connection!(UserRollConnection, UserRollEdge, UserRoll);
+connection!(MessageConnection, MessageEdge, Message);
+connection!(CocktailConnection, CocktailEdge, Cocktail);
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_cursor, encode_cursor, Page};
+
+    fn page(first: Option<i32>, after: Option<i64>, last: Option<i32>, before: Option<i64>, total: i64) -> (i64, i64) {
+        let page = Page::new(first, after.map(encode_cursor), last, before.map(encode_cursor), total).unwrap();
+
+        (page.start, page.end)
+    }
+
+    fn error_code(first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, total: i64) -> &'static str {
+        Page::new(first, after, last, before, total).unwrap_err().code()
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        for offset in &[0, 1, 42, 250] {
+            assert_eq!(decode_cursor(&encode_cursor(*offset), 250).unwrap(), *offset);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let cursors = vec![
+            "zz".to_owned(),
+            "not a cursor".to_owned(),
+            String::new(),
+            hex::encode("position:1"),
+            hex::encode("offset:one"),
+            hex::encode([0xff, 0xfe]),
+        ];
+        for cursor in &cursors {
+            assert_eq!(decode_cursor(cursor, 250).unwrap_err().code(), "INVALID_CURSOR");
+        }
+    }
+
+    #[test]
+    fn rejects_cursors_outside_the_results() {
+        assert_eq!(decode_cursor(&encode_cursor(251), 250).unwrap_err().code(), "INVALID_CURSOR");
+        assert_eq!(decode_cursor(&encode_cursor(-1), 250).unwrap_err().code(), "INVALID_CURSOR");
+        assert_eq!(error_code(None, Some(encode_cursor(i64::max_value())), None, None, 250), "INVALID_CURSOR");
+        assert_eq!(error_code(None, None, None, Some(encode_cursor(251)), 250), "INVALID_CURSOR");
+    }
+
+    #[test]
+    fn rejects_negative_page_sizes() {
+        assert_eq!(error_code(Some(-1), None, None, None, 250), "INVALID_PAGE_SIZE");
+        assert_eq!(error_code(None, None, Some(-1), None, 250), "INVALID_PAGE_SIZE");
+    }
+
+    #[test]
+    fn defaults_to_the_first_hundred_results() {
+        assert_eq!(page(None, None, None, None, 250), (0, 100));
+        assert_eq!(page(None, None, None, None, 20), (0, 20));
+        assert_eq!(page(None, Some(199), None, None, 250), (200, 250));
+    }
+
+    #[test]
+    fn first_counts_from_after() {
+        assert_eq!(page(Some(10), Some(4), None, None, 250), (5, 15));
+        assert_eq!(page(Some(10), Some(245), None, None, 250), (246, 250));
+        assert_eq!(page(Some(10), Some(249), None, None, 250), (250, 250));
+    }
+
+    #[test]
+    fn last_counts_back_from_before() {
+        assert_eq!(page(None, None, Some(5), Some(20), 250), (15, 20));
+        assert_eq!(page(None, None, Some(5), Some(3), 250), (0, 3));
+        assert_eq!(page(None, None, Some(5), None, 250), (245, 250));
+    }
+
+    #[test]
+    fn after_and_before_narrow_the_results() {
+        assert_eq!(page(Some(10), Some(4), None, Some(8), 250), (5, 8));
+        assert_eq!(page(None, Some(4), Some(2), Some(8), 250), (6, 8));
+        assert_eq!(page(None, Some(10), None, Some(5), 250), (11, 11));
+    }
+
+    #[test]
+    fn first_and_last_combine() {
+        assert_eq!(page(Some(10), None, Some(3), None, 250), (7, 10));
+        assert_eq!(page(Some(10), Some(19), Some(3), None, 250), (27, 30));
+    }
+
+    #[test]
+    fn constraints_match_the_page() {
+        let constraints = Page::new(Some(10), Some(encode_cursor(4)), None, None, 250).unwrap().constraints().unwrap();
+
+        assert_eq!((constraints.limit, constraints.offset), (10, 5));
+    }
+}
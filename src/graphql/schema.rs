@@ -1,54 +1,142 @@
 use std::convert::*;
 
-use chrono::NaiveDateTime;
-use juniper::FieldResult;
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+use chrono_tz::Tz;
 
+use crate::auth;
+use crate::bill::EventSummary;
 use crate::database::models as dbmodels;
+use crate::error::{Error, Result};
 use crate::graphql::{Context, MutationRoot, QueryRoot};
+use crate::graphql::order::{ApiTokenOrder, ChatOrder, CocktailOrder, EventOrder, IngredientOrder, MenuOrder, MessageOrder, UserOrder, UserRollOrder};
+use crate::graphql::pagination::{ChatConnection, CocktailConnection, EventConnection, MessageConnection, Page, UserConnection, UserRollConnection};
+use crate::graphql::validation::{Patch, Validate};
 use crate::models;
-use crate::models::{ChatUser, Cocktail, Event, EventUser, UserRoll};
+use crate::models::{Chat, ChatUser, Cocktail, CocktailCategory, Event, EventSchedule, EventUser, Menu, Message, User, UserRoll};
+use crate::scheduler;
+use crate::stats::{LeaderboardEntry, LeaderboardMetric, LeaderboardPeriod, StatsFilter, UserStats};
 
 #[derive(GraphQLInputObject)]
-struct InputChat {
-    pub chat_type: ChatType,
-    pub telegram_id: i32,
-    pub title: String,
-    pub description: Option<String>,
-    pub current_keyboard: KeyboardType,
-    pub spam_detection: bool,
+pub(crate) struct InputChat {
+    pub(crate) chat_type: ChatType,
+    pub(crate) telegram_id: i32,
+    pub(crate) title: String,
+    pub(crate) description: Option<String>,
+    pub(crate) current_keyboard: KeyboardType,
+    pub(crate) spam_detection: bool,
+    #[graphql(description = "IANA name like `Europe/Berlin`, new chats default to UTC")]
+    pub(crate) timezone: Option<String>,
 }
 
 impl From<InputChat> for dbmodels::ChatI {
     fn from(input: InputChat) -> Self {
         dbmodels::ChatI {
-            chat_type: input.chat_type as i16,
+            chat_type: input.chat_type.into(),
             telegram_id: input.telegram_id,
             title: input.title.clone(),
             description: input.description,
-            current_keyboard: input.current_keyboard as i16,
+            current_keyboard: input.current_keyboard.into(),
             spam_detection: input.spam_detection,
+            timezone: input.timezone,
         }
     }
 }
 
+#[derive(GraphQLInputObject)]
+struct InputChatPatch {
+    pub chat_type: Option<ChatType>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[graphql(description = "Removes the description")]
+    pub clear_description: Option<bool>,
+    pub current_keyboard: Option<KeyboardType>,
+    pub spam_detection: Option<bool>,
+    #[graphql(description = "IANA name like `Europe/Berlin`")]
+    pub timezone: Option<String>,
+    pub bill_rule: Option<models::BillRule>,
+    pub menu_id: Option<i32>,
+}
+
+impl InputChatPatch {
+    fn parse(self) -> Result<dbmodels::ChatPatch> {
+        Ok(dbmodels::ChatPatch {
+            chat_type: self.chat_type.map(Into::into),
+            title: self.title,
+            description: nullable("description", self.description, self.clear_description)?,
+            current_keyboard: self.current_keyboard.map(Into::into),
+            spam_detection: self.spam_detection,
+            timezone: self.timezone.map(validate_timezone).transpose()?,
+            bill_rule: self.bill_rule,
+            menu_id: self.menu_id,
+        })
+    }
+}
+
 impl From<InputEvent> for dbmodels::EventI {
     fn from(input: InputEvent) -> Self {
         dbmodels::EventI {
             chat_id: input.chat_id,
-            timestamp: input.timestamp,
-            active: input.active,
+            timestamp: input.timestamp.with_timezone(&Utc),
+            active: false,
+            menu_id: input.menu_id,
         }
     }
 }
 
 #[derive(GraphQLInputObject)]
-struct InputUser {
+struct InputEventPatch {
+    timestamp: Option<DateTime<FixedOffset>>,
+    #[graphql(description = "Overrides the menu of the chat")]
+    menu_id: Option<i32>,
+    #[graphql(description = "Falls back to the menu of the chat")]
+    clear_menu_id: Option<bool>,
+}
+
+impl InputEventPatch {
+    fn parse(self) -> Result<dbmodels::EventPatch> {
+        Ok(dbmodels::EventPatch {
+            timestamp: self.timestamp.map(|timestamp| timestamp.with_timezone(&Utc)),
+            menu_id: nullable("menuId", self.menu_id, self.clear_menu_id)?,
+        })
+    }
+}
+
+#[derive(GraphQLInputObject)]
+pub(crate) struct InputUser {
+    pub(crate) username: Option<String>,
+    pub(crate) first_name: String,
+    pub(crate) last_name: Option<String>,
+    pub(crate) telegram_id: i32,
+    pub(crate) is_bot: bool,
+    pub(crate) has_private_conversation: bool,
+}
+
+#[derive(GraphQLInputObject)]
+struct InputUserPatch {
     username: Option<String>,
-    first_name: String,
+    #[graphql(description = "Removes the username")]
+    clear_username: Option<bool>,
+    first_name: Option<String>,
     last_name: Option<String>,
-    telegram_id: i32,
-    is_bot: bool,
-    has_private_conversation: bool,
+    #[graphql(description = "Removes the last name")]
+    clear_last_name: Option<bool>,
+    is_bot: Option<bool>,
+    has_private_conversation: Option<bool>,
+    #[graphql(description = "Only roll non-alcoholic drinks for the user")]
+    non_alcoholic: Option<bool>,
+}
+
+impl InputUserPatch {
+    fn parse(self) -> Result<dbmodels::UserPatch> {
+        Ok(dbmodels::UserPatch {
+            username: nullable("username", self.username, self.clear_username)?,
+            first_name: self.first_name,
+            last_name: nullable("lastName", self.last_name, self.clear_last_name)?,
+            is_bot: self.is_bot,
+            has_private_conversation: self.has_private_conversation,
+            non_alcoholic: self.non_alcoholic,
+        })
+    }
 }
 
 impl From<InputUser> for dbmodels::UserI {
@@ -79,14 +167,17 @@ impl Default for Constraints {
     }
 }
 
-#[derive(juniper::GraphQLObject)]
-pub struct User {
-    pub id: i32,
-    pub username: Option<String>,
-    pub first_name: String,
-    pub last_name: Option<String>,
-    pub telegram_id: i32,
-    pub is_bot: bool,
+#[derive(Clone, Default, GraphQLInputObject)]
+pub struct CocktailFilter {
+    #[graphql(description = "Part of the name, case insensitive")]
+    pub(crate) name: Option<String>,
+    pub(crate) category_id: Option<i32>,
+    pub(crate) jumbo: Option<bool>,
+    pub(crate) alcoholic: Option<bool>,
+    #[graphql(description = "Only cocktails containing all of these ingredients")]
+    pub(crate) ingredient_ids: Option<Vec<i32>>,
+    #[graphql(description = "Only cocktails containing none of these ingredients")]
+    pub(crate) excluded_ingredient_ids: Option<Vec<i32>>,
 }
 
 #[derive(Debug, juniper::GraphQLEnum)]
@@ -106,6 +197,16 @@ impl From<models::ChatType> for ChatType {
     }
 }
 
+impl From<ChatType> for models::ChatType {
+    fn from(input: ChatType) -> Self {
+        match input {
+            ChatType::PRIVATE => models::ChatType::PRIVATE,
+            ChatType::GROUP => models::ChatType::GROUP,
+            ChatType::SUPERGROUP => models::ChatType::SUPERGROUP,
+        }
+    }
+}
+
 #[derive(Debug, juniper::GraphQLEnum)]
 pub enum KeyboardType {
     NONE = 0,
@@ -123,187 +224,1280 @@ impl From<models::KeyboardType> for KeyboardType {
     }
 }
 
-#[derive(juniper::GraphQLObject)]
-pub struct Chat {
-    id: i32,
-    chat_type: ChatType,
-    telegram_id: i32,
-    title: String,
-    description: Option<String>,
-    current_keyboard: KeyboardType,
-    spam_detection: bool,
-}
-
-impl From<models::Chat> for Chat {
-    fn from(input: models::Chat) -> Self {
-        Chat {
-            id: input.id,
-            chat_type: input.chat_type.into(),
-            telegram_id: input.telegram_id,
-            title: input.title.clone(),
-            description: input.description,
-            current_keyboard: input.current_keyboard.into(),
-            spam_detection: input.spam_detection,
+impl From<KeyboardType> for models::KeyboardType {
+    fn from(input: KeyboardType) -> Self {
+        match input {
+            KeyboardType::NONE => models::KeyboardType::NONE,
+            KeyboardType::ATTEND => models::KeyboardType::ATTEND,
+            KeyboardType::DICE => models::KeyboardType::DICE,
         }
     }
 }
 
-impl From<models::User> for User {
-    fn from(input: models::User) -> Self {
-        User {
-            id: input.id,
-            username: input.username,
-            first_name: input.first_name.clone(),
-            last_name: input.last_name,
-            telegram_id: input.telegram_id,
-            is_bot: input.is_bot,
-        }
+#[derive(GraphQLInputObject)]
+pub(crate) struct InputEvent {
+    pub(crate) chat_id: i32,
+    pub(crate) timestamp: DateTime<FixedOffset>,
+    #[graphql(description = "Overrides the menu of the chat")]
+    pub(crate) menu_id: Option<i32>,
+}
+
+/// Rolls may only be recorded while their event is active.
+fn require_active(event: &Event) -> Result<()> {
+    if event.active {
+        Ok(())
+    } else {
+        Err(Error::conflict("EVENT_NOT_ACTIVE", "The event is not active"))
     }
 }
 
 #[derive(GraphQLInputObject)]
-pub struct InputEvent {
+struct InputEventSchedule {
     chat_id: i32,
-    timestamp: NaiveDateTime,
-    active: bool,
+    weekday: models::Weekday,
+    #[graphql(description = "Formatted as `HH:MM`")]
+    time: String,
+    #[graphql(description = "IANA name like `Europe/Berlin`")]
+    timezone: String,
 }
 
-type InputUserRoll = dbmodels::UserRollI;
+impl InputEventSchedule {
+    fn parse(self) -> Result<dbmodels::EventScheduleI> {
+        Ok(dbmodels::EventScheduleI {
+            chat_id: self.chat_id,
+            weekday: self.weekday,
+            time: parse_time(&self.time)?,
+            timezone: validate_timezone(self.timezone)?,
+        })
+    }
+}
 
-#[juniper::object(Context = Context)]
-impl MutationRoot {
-    fn user(&self, context: &Context, user: InputUser) -> FieldResult<User> {
-        Ok(User::from(
-            models::User::from(
-                dbmodels::UserI::from(user)
-                    .insert(&context.connection.0)?
-            )
-        ))
+#[derive(GraphQLInputObject)]
+struct InputEventSchedulePatch {
+    weekday: Option<models::Weekday>,
+    #[graphql(description = "Formatted as `HH:MM`")]
+    time: Option<String>,
+    #[graphql(description = "IANA name like `Europe/Berlin`")]
+    timezone: Option<String>,
+}
+
+impl InputEventSchedulePatch {
+    fn parse(self) -> Result<dbmodels::EventSchedulePatch> {
+        Ok(dbmodels::EventSchedulePatch {
+            weekday: self.weekday,
+            time: self.time.map(|time| parse_time(&time)).transpose()?,
+            timezone: self.timezone.map(validate_timezone).transpose()?,
+        })
     }
+}
 
-    fn user_roll(&self, context: &Context, roll: InputUserRoll) -> FieldResult<UserRoll> {
-        let event_user_id = roll.event_user_id;
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| Error::validation("INVALID_TIME", "Times have to be formatted as `HH:MM`"))
+}
 
-        let connection = &context.connection.0;
-        let dbmodel: dbmodels::UserRoll = dbmodels::UserRollI::from(roll)
-            .insert(connection)?;
-        let event_user = models::EventUser::get_by_id(event_user_id, connection)?;
+fn validate_timezone(timezone: String) -> Result<String> {
+    match timezone.parse::<Tz>() {
+        Ok(_) => Ok(timezone),
+        Err(_) => Err(Error::validation("INVALID_TIMEZONE", "Unknown timezone, expected an IANA name like `Europe/Berlin`")),
+    }
+}
 
-        Ok(UserRoll {
-            id: dbmodel.id,
-            event_user,
-            jumbo: dbmodel.jumbo,
-            alcoholic: dbmodel.alcoholic,
-            roll: dbmodel.roll,
-            drink: dbmodel.drink,
-        })
+/// Juniper can't tell an explicit `null` from a missing field, so nullable columns are cleared through a flag instead.
+fn nullable<T>(field: &'static str, value: Option<T>, clear: Option<bool>) -> Result<Option<Option<T>>> {
+    match (value, clear.unwrap_or(false)) {
+        (Some(_), true) => Err(Error::validation("INVALID_PATCH", format!("`{}` can't be set and cleared at once", field))),
+        (None, true) => Ok(Some(None)),
+        (value, false) => Ok(value.map(Some)),
     }
+}
 
-    fn chat(&self, context: &Context, chat: InputChat) -> FieldResult<Chat> {
-        let dbmodel: dbmodels::Chat = dbmodels::ChatI::from(chat)
-            .insert(&context.connection.0)?;
-        let model: models::Chat = dbmodel.into();
+#[derive(GraphQLInputObject)]
+struct InputCocktail {
+    menu_id: i32,
+    name: String,
+    jumbo: bool,
+    alcoholic: bool,
+    category_id: i32,
+    #[graphql(description = "In cents")]
+    price: Option<i32>,
+    #[graphql(description = "Price of the jumbo size in cents")]
+    jumbo_price: Option<i32>,
+}
 
-        Ok(model.into())
+impl From<InputCocktail> for dbmodels::CocktailI {
+    fn from(input: InputCocktail) -> Self {
+        dbmodels::CocktailI {
+            menu_id: input.menu_id,
+            name: input.name,
+            jumbo: input.jumbo,
+            alcoholic: input.alcoholic,
+            category: input.category_id,
+            price: input.price,
+            jumbo_price: input.jumbo_price,
+        }
     }
+}
 
-    fn chat_user(&self, context: &Context, chat_user: dbmodels::ChatUserI) -> FieldResult<ChatUser> {
-        let connection = &context.connection.0;
-        let dbmodel = chat_user.insert(connection)?;
-        let chat: models::Chat = dbmodels::Chat::get_by_id(dbmodel.chat_id, connection)?.into();
-        let user: models::User = dbmodels::User::get_by_id(dbmodel.user_id, connection)?.into();
+#[derive(GraphQLInputObject)]
+struct InputCocktailPatch {
+    menu_id: Option<i32>,
+    name: Option<String>,
+    jumbo: Option<bool>,
+    alcoholic: Option<bool>,
+    category_id: Option<i32>,
+    #[graphql(description = "In cents")]
+    price: Option<i32>,
+    #[graphql(description = "Removes the price")]
+    clear_price: Option<bool>,
+    #[graphql(description = "Price of the jumbo size in cents")]
+    jumbo_price: Option<i32>,
+    #[graphql(description = "Removes the price of the jumbo size")]
+    clear_jumbo_price: Option<bool>,
+    #[graphql(description = "Set to `false` to put a retired cocktail back on the menu")]
+    retired: Option<bool>,
+}
 
-        Ok(ChatUser {
-            id: dbmodel.id,
-            user,
-            chat,
-            spamming: dbmodel.spamming,
-            muted: dbmodel.muted,
-            admin: dbmodel.admin,
+impl InputCocktailPatch {
+    fn parse(self) -> Result<dbmodels::CocktailPatch> {
+        Ok(dbmodels::CocktailPatch {
+            menu_id: self.menu_id,
+            name: self.name,
+            jumbo: self.jumbo,
+            alcoholic: self.alcoholic,
+            category: self.category_id,
+            price: nullable("price", self.price, self.clear_price)?,
+            jumbo_price: nullable("jumboPrice", self.jumbo_price, self.clear_jumbo_price)?,
+            retired: self.retired,
         })
     }
+}
 
-    fn event_user(&self, context: &Context, event_user: dbmodels::EventUserI) -> FieldResult<EventUser> {
-        let connection = &context.connection.0;
-        let dbmodel = event_user.insert(connection)?;
-        let event = dbmodels::Event::get_by_id(dbmodel.event_id, connection)?.into_model_event(connection)?;
-        let user = dbmodels::User::get_by_id(dbmodel.user_id, connection)?;
+type InputUserRoll = dbmodels::UserRollI;
 
-        Ok(EventUser {
-            id: dbmodel.id,
-            event,
-            user,
-            attends: dbmodel.attends,
-        })
+#[derive(GraphQLObject)]
+struct NewApiToken {
+    api_token: dbmodels::ApiToken,
+    #[graphql(description = "The token itself, it is not stored and can't be retrieved again")]
+    token: String,
+}
+
+#[juniper::object(Context = Context)]
+impl Cocktail {
+    fn id(&self) -> i32 {
+        self.id
     }
 
-    fn event(&self, context: &Context, event: InputEvent) -> FieldResult<Event> {
-        let connection = &context.connection.0;
-        let dbmodel = dbmodels::EventI::from(event).insert(connection)?;
-        let chat: dbmodels::Chat = dbmodels::Chat::get_by_id(dbmodel.chat_id, connection)?;
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn jumbo(&self) -> bool {
+        self.jumbo
+    }
 
-        let model: models::Event = dbmodel.into_model_event(connection)?;
+    fn alcoholic(&self) -> bool {
+        self.alcoholic
+    }
+
+    fn category(&self, context: &Context) -> Result<CocktailCategory> {
+        context.loaders.cocktail_categories
+            .load(self.category_id, &context.connection.0)?
+            .ok_or_else(|| diesel::NotFound.into())
+    }
+
+    #[graphql(description = "In cents")]
+    fn price(&self) -> Option<i32> {
+        self.price
+    }
+
+    #[graphql(description = "Price of the jumbo size in cents")]
+    fn jumbo_price(&self) -> Option<i32> {
+        self.jumbo_price
+    }
+
+    #[graphql(description = "Retired cocktails are no longer rolled")]
+    fn retired(&self) -> bool {
+        self.retired
+    }
+
+    fn menu(&self, context: &Context) -> Result<Menu> {
+        context.loaders.menus
+            .load(self.menu_id, &context.connection.0)?
+            .ok_or_else(|| diesel::NotFound.into())
+    }
 
-        Ok(model.into())
+    fn ingredients(&self, context: &Context) -> Result<Vec<dbmodels::Ingredient>> {
+        Ok(context.loaders.cocktail_ingredients
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
     }
 }
 
-#[derive(GraphQLInputObject)]
-pub(crate) struct ChatQuery {
-    pub(crate) id: Option<i32>,
-    pub(crate) chat_type: Option<ChatType>,
-    pub(crate) telegram_id: Option<i32>,
-    pub(crate) title: Option<String>,
-    pub(crate) description: Option<String>,
-    pub(crate) current_keyboard: Option<KeyboardType>,
-    pub(crate) spam_detection: Option<bool>,
+#[juniper::object(Context = Context, description = "A bar with its own cocktails and prices")]
+impl Menu {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[graphql(description = "Retired cocktails are left out unless `includeRetired` is set")]
+    fn cocktails(&self, context: &Context, constraints: Option<Constraints>, filter: Option<CocktailFilter>, order_by: Option<Vec<CocktailOrder>>, include_retired: Option<bool>) -> Result<Vec<Cocktail>> {
+        let constraints = constraints.unwrap_or_default();
+        let filter = filter.unwrap_or_default();
+
+        let cocktails = Cocktail::get(&context.connection.0, constraints, filter, &order_by.unwrap_or_default(), Some(self.id), include_retired.unwrap_or(false))?;
+        context.loaders.cocktail_ingredients.register(cocktails.iter().map(|cocktail| cocktail.id));
+        context.loaders.cocktail_categories.register(cocktails.iter().map(|cocktail| cocktail.category_id));
+
+        Ok(cocktails)
+    }
 }
 
 #[juniper::object(Context = Context)]
-impl QueryRoot {
-    #[graphql(description = "All fields inside `query` are joined by an OR")]
-    fn chats(&self, context: &Context, constraints: Option<Constraints>, query: Option<ChatQuery>) -> FieldResult<Vec<Chat>> {
-        let constraints = constraints.unwrap_or_default();
+impl Chat {
+    fn id(&self) -> i32 {
+        self.id
+    }
 
-        // performance optimization, if `query` is undefined, we can just get all chats (limited by `constraints`)
-        Ok(match query {
-            Some(val) => models::Chat::get_by_query(&context.connection.0, constraints, val),
-            None => models::Chat::get(&context.connection.0, constraints)
-        }?.into_iter()
-            .map(Into::into)
-            .collect())
+    fn chat_type(&self) -> &models::ChatType {
+        &self.chat_type
     }
 
-    fn events(&self, context: &Context, constraints: Option<Constraints>) -> FieldResult<Vec<models::Event>> {
-        let constraints = constraints.unwrap_or_default();
+    fn telegram_id(&self) -> i32 {
+        self.telegram_id
+    }
 
-        Ok(models::Event::get(&context.connection.0, constraints)?
-            .into_iter()
-            .map(Into::into)
-            .collect())
+    fn title(&self) -> &str {
+        &self.title
     }
 
-    fn users(&self, context: &Context, constraints: Option<Constraints>) -> FieldResult<Vec<User>> {
-        let constraints = constraints.unwrap_or_default();
+    fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(String::as_str)
+    }
+
+    fn current_keyboard(&self) -> &models::KeyboardType {
+        &self.current_keyboard
+    }
+
+    fn spam_detection(&self) -> bool {
+        self.spam_detection
+    }
+
+    #[graphql(description = "IANA name like `Europe/Berlin`, timestamps of the chat are returned in it")]
+    fn timezone(&self) -> &str {
+        &self.timezone
+    }
 
-        Ok(models::User::get(&context.connection.0, constraints)?
+    #[graphql(description = "Default rule for splitting the bill of an event")]
+    fn bill_rule(&self) -> models::BillRule {
+        self.bill_rule
+    }
+
+    #[graphql(description = "Default menu of the chat's events")]
+    fn menu(&self, context: &Context) -> Result<Menu> {
+        context.loaders.menus
+            .load(self.menu_id, &context.connection.0)?
+            .ok_or_else(|| diesel::NotFound.into())
+    }
+
+    fn members(&self, context: &Context) -> Result<Vec<ChatUser>> {
+        Ok(context.loaders.chat_members
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
+    }
+
+    fn events(&self, context: &Context) -> Result<Vec<Event>> {
+        let events = context.loaders.chat_events
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default();
+        context.loaders.register_events(&events);
+
+        Ok(events)
+    }
+
+    #[graphql(description = "The first event which hasn't started yet")]
+    fn next_event(&self, context: &Context) -> Result<Option<Event>> {
+        let now = Utc::now();
+
+        Ok(context.loaders.chat_events
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default()
             .into_iter()
-            .map(Into::into)
-            .collect())
+            .find(|event| event.timestamp.with_timezone(&Utc) > now))
     }
 
-    fn user_rolls(&self, context: &Context, constraints: Option<Constraints>) -> FieldResult<Vec<UserRoll>> {
-        let constraints = constraints.unwrap_or_default();
+    fn schedules(&self, context: &Context) -> Result<Vec<EventSchedule>> {
+        Ok(context.loaders.chat_schedules
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
+    }
 
-        Ok(models::UserRoll::get(&context.connection.0, constraints)?
+    fn active_event(&self, context: &Context) -> Result<Option<Event>> {
+        Ok(context.loaders.chat_events
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default()
             .into_iter()
-            .map(Into::into)
-            .collect())
+            .find(|event| event.active))
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl User {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn username(&self) -> Option<&str> {
+        self.username.as_ref().map(String::as_str)
+    }
+
+    fn first_name(&self) -> &str {
+        &self.first_name
+    }
+
+    fn last_name(&self) -> Option<&str> {
+        self.last_name.as_ref().map(String::as_str)
+    }
+
+    fn telegram_id(&self) -> i32 {
+        self.telegram_id
+    }
+
+    fn is_bot(&self) -> bool {
+        self.is_bot
+    }
+
+    fn has_private_conversation(&self) -> bool {
+        self.has_private_conversation
+    }
+
+    #[graphql(description = "The user only rolls non-alcoholic drinks")]
+    fn non_alcoholic(&self) -> bool {
+        self.non_alcoholic
+    }
+
+    #[graphql(description = "Drinks containing any of these ingredients are never rolled for the user")]
+    fn excluded_ingredients(&self, context: &Context) -> Result<Vec<dbmodels::Ingredient>> {
+        Ok(context.loaders.user_excluded_ingredients
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
+    }
+
+    fn chats(&self, context: &Context) -> Result<Vec<ChatUser>> {
+        Ok(context.loaders.user_chats
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
+    }
+
+    #[graphql(description = "All events the user has been invited to, `attends` tells whether they actually attend")]
+    fn events(&self, context: &Context) -> Result<Vec<EventUser>> {
+        Ok(context.loaders.user_events
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl Event {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn chat(&self) -> &Chat {
+        &self.chat
+    }
+
+    fn timestamp(&self) -> DateTime<FixedOffset> {
+        self.timestamp
+    }
+
+    fn active(&self) -> bool {
+        self.active
+    }
+
+    #[graphql(description = "The menu of the event if set, the menu of the chat otherwise")]
+    fn menu(&self, context: &Context) -> Result<Menu> {
+        context.loaders.menus
+            .load(self.effective_menu_id(), &context.connection.0)?
+            .ok_or_else(|| diesel::NotFound.into())
+    }
+
+    #[graphql(description = "All users invited to the event, `attends` tells whether they actually attend")]
+    fn attendees(&self, context: &Context) -> Result<Vec<EventUser>> {
+        Ok(context.loaders.event_attendees
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default())
+    }
+
+    fn rolls(&self, context: &Context) -> Result<Vec<UserRoll>> {
+        let rolls = context.loaders.event_rolls
+            .load(self.id, &context.connection.0)?
+            .unwrap_or_default();
+        context.loaders.register_rolls(&rolls);
+
+        Ok(rolls)
+    }
+}
+
+#[juniper::object(Context = Context, description = "Events of the chat are created every `weekday` at `time` in `timezone`")]
+impl EventSchedule {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn chat(&self) -> &Chat {
+        &self.chat
+    }
+
+    fn weekday(&self) -> models::Weekday {
+        self.weekday
+    }
+
+    #[graphql(description = "Formatted as `HH:MM`")]
+    fn time(&self) -> String {
+        self.time.format("%H:%M").to_string()
+    }
+
+    #[graphql(description = "IANA name like `Europe/Berlin`")]
+    fn timezone(&self) -> &str {
+        &self.timezone
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl UserRoll {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn event_user(&self) -> &EventUser {
+        &self.event_user
+    }
+
+    fn jumbo(&self) -> bool {
+        self.jumbo
+    }
+
+    fn alcoholic(&self) -> bool {
+        self.alcoholic
+    }
+
+    fn roll(&self) -> i32 {
+        self.roll
+    }
+
+    fn drink(&self) -> &str {
+        &self.drink
+    }
+
+    #[graphql(description = "The cocktail `drink` refers to, if it is part of the menu of the event")]
+    fn cocktail(&self, context: &Context) -> Result<Option<Cocktail>> {
+        Ok(context.loaders.cocktails_by_name.load((self.event_user.event.effective_menu_id(), self.drink.clone()), &context.connection.0)?)
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl Message {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn telegram_id(&self) -> i32 {
+        self.telegram_id
+    }
+
+    fn chat(&self) -> &models::Chat {
+        &self.chat
+    }
+
+    fn user(&self) -> Option<&models::User> {
+        self.user.as_ref()
+    }
+
+    fn timestamp(&self) -> DateTime<FixedOffset> {
+        self.timestamp
+    }
+
+    fn reply_to_message_id(&self) -> Option<i32> {
+        self.reply_to_message_id
+    }
+
+    #[graphql(description = "The message this one replies to, if it is known to the backend")]
+    fn reply_to(&self, context: &Context) -> Result<Option<Message>> {
+        Ok(match self.reply_to_message_id {
+            Some(telegram_id) => context.loaders.messages.load((self.chat.id, telegram_id), &context.connection.0)?,
+            None => None,
+        })
+    }
+
+    #[graphql(description = "All known messages replying to this one")]
+    fn replies(&self, context: &Context) -> Result<Vec<Message>> {
+        let messages = context.loaders.message_replies
+            .load((self.chat.id, self.telegram_id), &context.connection.0)?
+            .unwrap_or_default();
+        context.loaders.register_messages(&messages);
+
+        Ok(messages)
+    }
+
+    fn edit_timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        self.edit_timestamp
+    }
+
+    fn text(&self) -> Option<&str> {
+        self.text.as_ref().map(String::as_str)
+    }
+
+    fn caption(&self) -> Option<&str> {
+        self.caption.as_ref().map(String::as_str)
+    }
+
+    fn new_chat_members(&self, context: &Context) -> Result<Vec<User>> {
+        Ok(context.loaders.users
+            .load_many(&self.new_chat_member_ids, &context.connection.0)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    fn left_chat_member(&self, context: &Context) -> Result<Option<User>> {
+        Ok(match self.left_chat_member_id {
+            Some(id) => context.loaders.users.load(id, &context.connection.0)?,
+            None => None,
+        })
+    }
+
+    fn new_chat_title(&self) -> Option<&str> {
+        self.new_chat_title.as_ref().map(String::as_str)
+    }
+
+    fn group_chat_created(&self) -> Option<bool> {
+        self.group_chat_created
+    }
+
+    fn supergroup_chat_created(&self) -> Option<bool> {
+        self.supergroup_chat_created
+    }
+
+    fn migrate_to_chat(&self, context: &Context) -> Result<Option<Chat>> {
+        Ok(match self.migrate_to_chat_id {
+            Some(id) => context.loaders.chats.load(id, &context.connection.0)?,
+            None => None,
+        })
+    }
+
+    fn migrate_from_chat(&self, context: &Context) -> Result<Option<Chat>> {
+        Ok(match self.migrate_from_chat_id {
+            Some(id) => context.loaders.chats.load(id, &context.connection.0)?,
+            None => None,
+        })
+    }
+
+    fn pinned_message_id(&self) -> Option<i32> {
+        self.pinned_message_id
+    }
+
+    #[graphql(description = "The pinned message, if it is known to the backend")]
+    fn pinned_message(&self, context: &Context) -> Result<Option<Message>> {
+        Ok(match self.pinned_message_id {
+            Some(telegram_id) => context.loaders.messages.load((self.chat.id, telegram_id), &context.connection.0)?,
+            None => None,
+        })
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl MutationRoot {
+    fn user(&self, context: &Context, user: InputUser) -> Result<User> {
+        context.token()?;
+        user.validate(&context.connection.0)?;
+
+        Ok(dbmodels::UserI::from(user)
+            .insert(&context.connection.0)?)
+    }
+
+    #[graphql(description = "Manual correction of a roll, restricted to admin tokens. Clients roll through `rollDice`. Refused unless the event is active")]
+    fn user_roll(&self, context: &Context, roll: InputUserRoll) -> Result<UserRoll> {
+        context.admin()?;
+
+        let connection = &context.connection.0;
+        roll.validate(connection)?;
+        let event_user = models::EventUser::get_by_id(roll.event_user_id, connection)?;
+        require_active(&event_user.event)?;
+
+        let dbmodel: dbmodels::UserRoll = dbmodels::UserRollI::from(roll)
+            .insert(connection)?;
+
+        Ok(UserRoll {
+            id: dbmodel.id,
+            event_user,
+            jumbo: dbmodel.jumbo,
+            alcoholic: dbmodel.alcoholic,
+            roll: dbmodel.roll,
+            drink: dbmodel.drink,
+        })
+    }
+
+    #[graphql(description = "Rolls the dice for `event_user_id` and picks a matching cocktail. Calling this again with the same `callback_query_id` returns the existing roll. Refused unless the event is active. Logged in users can only roll for themselves.")]
+    fn roll_dice(&self, context: &Context, event_user_id: i32, callback_query_id: String, jumbo: bool, alcoholic: bool) -> Result<UserRoll> {
+        context.authenticated()?;
+
+        let connection = &context.connection.0;
+        let event_user = EventUser::get_by_id(event_user_id, connection)?;
+        context.token_or_self(event_user.user.id)?;
+        require_active(&event_user.event)?;
+
+        Ok(UserRoll::roll_dice(event_user_id, callback_query_id, jumbo, alcoholic, connection)?)
+    }
+
+    #[graphql(description = "Changing `current_keyboard` or `spam_detection` of an existing chat requires a chat admin")]
+    fn chat(&self, context: &Context, chat: InputChat) -> Result<Chat> {
+        context.token()?;
+        chat.validate(&context.connection.0)?;
+
+        let chat = dbmodels::ChatI::from(chat);
+        if let Some(existing) = dbmodels::Chat::get_by_telegram_id(chat.telegram_id, &context.connection.0)? {
+            if existing.current_keyboard != chat.current_keyboard || existing.spam_detection != chat.spam_detection {
+                context.chat_admin(existing.id)?;
+            }
+        }
+
+        let dbmodel: dbmodels::Chat = chat.insert(&context.connection.0)?;
+        Ok(dbmodel.into())
+    }
+
+    #[graphql(description = "Setting or changing `admin`, `muted` or `spamming` requires a chat admin")]
+    fn chat_user(&self, context: &Context, chat_user: dbmodels::ChatUserI) -> Result<ChatUser> {
+        context.token()?;
+
+        let connection = &context.connection.0;
+        let changes_role = match dbmodels::ChatUser::get_by_chat_and_user(chat_user.chat_id, chat_user.user_id, connection)? {
+            Some(existing) => existing.admin != chat_user.admin || existing.muted != chat_user.muted || existing.spamming != chat_user.spamming,
+            None => chat_user.admin || chat_user.muted || chat_user.spamming,
+        };
+        if changes_role {
+            context.chat_admin(chat_user.chat_id)?;
+        }
+
+        let dbmodel = chat_user.insert(connection)?;
+        let chat: models::Chat = dbmodels::Chat::get_by_id(dbmodel.chat_id, connection)?.into();
+        let user: models::User = dbmodels::User::get_by_id(dbmodel.user_id, connection)?.into();
+
+        Ok(ChatUser {
+            id: dbmodel.id,
+            user,
+            chat,
+            spamming: dbmodel.spamming,
+            muted: dbmodel.muted,
+            admin: dbmodel.admin,
+        })
+    }
+
+    fn event_user(&self, context: &Context, event_user: dbmodels::EventUserI) -> Result<EventUser> {
+        context.token()?;
+
+        let connection = &context.connection.0;
+        let dbmodel = event_user.insert(connection)?;
+        let event = dbmodels::Event::get_by_id(dbmodel.event_id, connection)?.into_model_event(connection)?;
+        let user = dbmodels::User::get_by_id(dbmodel.user_id, connection)?;
+
+        Ok(EventUser {
+            id: dbmodel.id,
+            event,
+            user,
+            attends: dbmodel.attends,
+        })
+    }
+
+    #[graphql(description = "Requires a chat admin. New events are inactive, use `openEvent` to start one")]
+    fn event(&self, context: &Context, event: InputEvent) -> Result<Event> {
+        context.chat_admin(event.chat_id)?;
+
+        let connection = &context.connection.0;
+        event.validate(connection)?;
+        let dbmodel = dbmodels::EventI::from(event).insert(connection)?;
+
+        Ok(dbmodel.into_model_event(connection)?)
+    }
+
+    #[graphql(description = "Requires a chat admin. Activates the event at `timestamp`, creating it if necessary. Refused while the chat already has an active event")]
+    fn open_event(&self, context: &Context, chat_id: i32, timestamp: DateTime<FixedOffset>) -> Result<Event> {
+        context.chat_admin(chat_id)?;
+
+        let timestamp = timestamp.with_timezone(&Utc);
+        let connection = &context.connection.0;
+        if let Some(active) = dbmodels::Event::get_active_by_chat_id(chat_id, connection)? {
+            if active.timestamp != timestamp {
+                return Err(Error::conflict("EVENT_ALREADY_ACTIVE", "The chat already has an active event"));
+            }
+        }
+
+        Ok(dbmodels::Event::open(chat_id, timestamp, connection)?.into_model_event(connection)?)
+    }
+
+    #[graphql(description = "Requires a chat admin")]
+    fn close_event(&self, context: &Context, event_id: i32) -> Result<Event> {
+        context.authenticated()?;
+
+        let connection = &context.connection.0;
+        context.chat_admin(dbmodels::Event::get_by_id(event_id, connection)?.chat_id)?;
+
+        Ok(dbmodels::Event::close(event_id, connection)?.into_model_event(connection)?)
+    }
+
+    #[graphql(description = "Logged in users can only change `nonAlcoholic` of themselves, everything else is kept in sync with telegram by the bot")]
+    fn update_user(&self, context: &Context, id: i32, patch: InputUserPatch) -> Result<User> {
+        context.token_or_self(id)?;
+        let patch = patch.parse()?;
+        patch.require_changes()?;
+        if patch.username.is_some() || patch.first_name.is_some() || patch.last_name.is_some() || patch.is_bot.is_some() || patch.has_private_conversation.is_some() {
+            context.token()?;
+        }
+
+        Ok(patch.update(id, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Deleting a user who is still part of chats or events is refused unless `cascade` is set")]
+    fn delete_user(&self, context: &Context, id: i32, cascade: Option<bool>) -> Result<User> {
+        context.token()?;
+
+        Ok(dbmodels::User::delete(id, cascade.unwrap_or(false), &context.connection.0)?)
+    }
+
+    #[graphql(description = "Changing `chat_type`, `title` or `description` requires an API token, they are kept in sync with telegram by the bot. Everything else requires a chat admin")]
+    fn update_chat(&self, context: &Context, id: i32, patch: InputChatPatch) -> Result<Chat> {
+        context.authenticated()?;
+        if patch.chat_type.is_some() || patch.title.is_some() || patch.description.is_some() || patch.clear_description.is_some() {
+            context.token()?;
+        }
+        if patch.current_keyboard.is_some() || patch.spam_detection.is_some() || patch.timezone.is_some() || patch.bill_rule.is_some() || patch.menu_id.is_some() {
+            context.chat_admin(id)?;
+        }
+        let patch = patch.parse()?;
+        patch.require_changes()?;
+
+        Ok(patch
+            .update(id, &context.connection.0)?
+            .into())
+    }
+
+    #[graphql(description = "Requires a chat admin. Deleting a chat which still has members, events, schedules or messages is refused unless `cascade` is set")]
+    fn delete_chat(&self, context: &Context, id: i32, cascade: Option<bool>) -> Result<Chat> {
+        context.chat_admin(id)?;
+
+        Ok(dbmodels::Chat::delete(id, cascade.unwrap_or(false), &context.connection.0)?
+            .into())
+    }
+
+    #[graphql(description = "Requires a chat admin")]
+    fn update_chat_user(&self, context: &Context, id: i32, patch: dbmodels::ChatUserPatch) -> Result<ChatUser> {
+        context.authenticated()?;
+        patch.require_changes()?;
+
+        let connection = &context.connection.0;
+        context.chat_admin(ChatUser::get_by_id(id, connection)?.chat.id)?;
+        patch.update(id, connection)?;
+
+        Ok(ChatUser::get_by_id(id, connection)?)
+    }
+
+    #[graphql(description = "Requires a chat admin, unless users remove themselves")]
+    fn delete_chat_user(&self, context: &Context, id: i32) -> Result<ChatUser> {
+        context.token()?;
+
+        let connection = &context.connection.0;
+        let chat_user = ChatUser::get_by_id(id, connection)?;
+        if context.telegram_id() != Some(chat_user.user.telegram_id) {
+            context.chat_admin(chat_user.chat.id)?;
+        }
+        dbmodels::ChatUser::delete(id, connection)?;
+
+        Ok(chat_user)
+    }
+
+    #[graphql(description = "Requires a chat admin")]
+    fn update_event(&self, context: &Context, id: i32, patch: InputEventPatch) -> Result<Event> {
+        context.authenticated()?;
+        let patch = patch.parse()?;
+        patch.require_changes()?;
+
+        let connection = &context.connection.0;
+        context.chat_admin(dbmodels::Event::get_by_id(id, connection)?.chat_id)?;
+
+        Ok(patch.update(id, connection)?.into_model_event(connection)?)
+    }
+
+    #[graphql(description = "Requires a chat admin. Deleting an event which still has users is refused unless `cascade` is set")]
+    fn delete_event(&self, context: &Context, id: i32, cascade: Option<bool>) -> Result<Event> {
+        context.authenticated()?;
+
+        let connection = &context.connection.0;
+        let event = Event::get_by_id(id, connection)?;
+        context.chat_admin(event.chat.id)?;
+        dbmodels::Event::delete(id, cascade.unwrap_or(false), connection)?;
+
+        Ok(event)
+    }
+
+    #[graphql(description = "Requires a chat admin. The next event of the schedule is created right away")]
+    fn event_schedule(&self, context: &Context, schedule: InputEventSchedule) -> Result<EventSchedule> {
+        context.chat_admin(schedule.chat_id)?;
+
+        let connection = &context.connection.0;
+        let dbmodel = schedule.parse()?.insert(connection)?;
+        scheduler::materialize_event(&dbmodel, Utc::now(), connection)?;
+
+        Ok(EventSchedule::get_by_id(dbmodel.id, connection)?)
+    }
+
+    #[graphql(description = "Requires a chat admin. Events which were already created are left untouched")]
+    fn update_event_schedule(&self, context: &Context, id: i32, patch: InputEventSchedulePatch) -> Result<EventSchedule> {
+        context.authenticated()?;
+
+        let connection = &context.connection.0;
+        context.chat_admin(dbmodels::EventSchedule::get_by_id(id, connection)?.chat_id)?;
+        let patch = patch.parse()?;
+        patch.require_changes()?;
+        let dbmodel = patch.update(id, connection)?;
+        scheduler::materialize_event(&dbmodel, Utc::now(), connection)?;
+
+        Ok(EventSchedule::get_by_id(id, connection)?)
+    }
+
+    #[graphql(description = "Requires a chat admin. Events which were already created are left untouched")]
+    fn delete_event_schedule(&self, context: &Context, id: i32) -> Result<EventSchedule> {
+        context.authenticated()?;
+
+        let connection = &context.connection.0;
+        let schedule = EventSchedule::get_by_id(id, connection)?;
+        context.chat_admin(schedule.chat.id)?;
+        dbmodels::EventSchedule::delete(id, connection)?;
+
+        Ok(schedule)
+    }
+
+    #[graphql(description = "Logged in users can only change their own invitations")]
+    fn update_event_user(&self, context: &Context, id: i32, patch: dbmodels::EventUserPatch) -> Result<EventUser> {
+        context.authenticated()?;
+        patch.require_changes()?;
+
+        let connection = &context.connection.0;
+        context.token_or_self(EventUser::get_by_id(id, connection)?.user.id)?;
+        patch.update(id, connection)?;
+
+        Ok(EventUser::get_by_id(id, connection)?)
+    }
+
+    #[graphql(description = "Deleting an event user who already rolled is refused unless `cascade` is set")]
+    fn delete_event_user(&self, context: &Context, id: i32, cascade: Option<bool>) -> Result<EventUser> {
+        context.token()?;
+
+        let connection = &context.connection.0;
+        let event_user = EventUser::get_by_id(id, connection)?;
+        dbmodels::EventUser::delete(id, cascade.unwrap_or(false), connection)?;
+
+        Ok(event_user)
+    }
+
+    #[graphql(description = "Manual correction of a roll, restricted to admin tokens")]
+    fn update_user_roll(&self, context: &Context, id: i32, patch: dbmodels::UserRollPatch) -> Result<UserRoll> {
+        context.admin()?;
+        patch.require_changes()?;
+
+        let connection = &context.connection.0;
+        patch.update(id, connection)?;
+
+        Ok(UserRoll::get_by_id(id, connection)?)
+    }
+
+    #[graphql(description = "Manual correction of a roll, restricted to admin tokens")]
+    fn delete_user_roll(&self, context: &Context, id: i32) -> Result<UserRoll> {
+        context.admin()?;
+
+        let connection = &context.connection.0;
+        let user_roll = UserRoll::get_by_id(id, connection)?;
+        dbmodels::UserRoll::delete(id, connection)?;
+
+        Ok(user_roll)
+    }
+
+    fn create_api_token(&self, context: &Context, name: String, admin: bool) -> Result<NewApiToken> {
+        context.admin()?;
+
+        let token = auth::generate_token();
+        let api_token = dbmodels::ApiTokenI {
+            name,
+            token_hash: auth::hash_token(&token),
+            admin,
+        }.insert(&context.connection.0)?;
+
+        Ok(NewApiToken { api_token, token })
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn cocktail_category(&self, context: &Context, category: dbmodels::CocktailCategoryI) -> Result<CocktailCategory> {
+        context.admin()?;
+
+        Ok(category.insert(&context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn update_cocktail_category(&self, context: &Context, id: i32, category: dbmodels::CocktailCategoryI) -> Result<CocktailCategory> {
+        context.admin()?;
+
+        Ok(category.update(id, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token. Deleting a category which is still used by cocktails is refused")]
+    fn delete_cocktail_category(&self, context: &Context, id: i32) -> Result<CocktailCategory> {
+        context.admin()?;
+
+        Ok(CocktailCategory::delete(id, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn menu(&self, context: &Context, menu: dbmodels::MenuI) -> Result<Menu> {
+        context.admin()?;
+
+        Ok(menu.insert(&context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn update_menu(&self, context: &Context, id: i32, menu: dbmodels::MenuI) -> Result<Menu> {
+        context.admin()?;
+
+        Ok(menu.update(id, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token. Deleting a menu which is still used by cocktails, chats or events is refused")]
+    fn delete_menu(&self, context: &Context, id: i32) -> Result<Menu> {
+        context.admin()?;
+
+        Ok(dbmodels::Menu::delete(id, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn cocktail(&self, context: &Context, cocktail: InputCocktail) -> Result<Cocktail> {
+        context.admin()?;
+
+        let connection = &context.connection.0;
+        let dbmodel = dbmodels::CocktailI::from(cocktail).insert(connection)?;
+
+        Ok(Cocktail::get_by_id(dbmodel.id, connection)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn update_cocktail(&self, context: &Context, id: i32, patch: InputCocktailPatch) -> Result<Cocktail> {
+        context.admin()?;
+
+        let patch = patch.parse()?;
+        patch.require_changes()?;
+
+        let connection = &context.connection.0;
+        patch.update(id, connection)?;
+
+        Ok(Cocktail::get_by_id(id, connection)?)
+    }
+
+    #[graphql(description = "Requires an admin token. Takes the cocktail off the menu, past rolls keep referring to it")]
+    fn retire_cocktail(&self, context: &Context, id: i32) -> Result<Cocktail> {
+        context.admin()?;
+
+        let connection = &context.connection.0;
+        dbmodels::CocktailPatch {
+            menu_id: None,
+            name: None,
+            jumbo: None,
+            alcoholic: None,
+            category: None,
+            price: None,
+            jumbo_price: None,
+            retired: Some(true),
+        }.update(id, connection)?;
+
+        Ok(Cocktail::get_by_id(id, connection)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn ingredient(&self, context: &Context, ingredient: dbmodels::IngredientI) -> Result<dbmodels::Ingredient> {
+        context.admin()?;
+
+        Ok(ingredient.insert(&context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn update_ingredient(&self, context: &Context, id: i32, ingredient: dbmodels::IngredientI) -> Result<dbmodels::Ingredient> {
+        context.admin()?;
+
+        Ok(ingredient.update(id, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token. Deleting an ingredient which is still used by cocktails is refused unless `cascade` is set")]
+    fn delete_ingredient(&self, context: &Context, id: i32, cascade: Option<bool>) -> Result<dbmodels::Ingredient> {
+        context.admin()?;
+
+        Ok(dbmodels::Ingredient::delete(id, cascade.unwrap_or(false), &context.connection.0)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn add_cocktail_ingredient(&self, context: &Context, cocktail_id: i32, ingredient_id: i32) -> Result<Cocktail> {
+        context.admin()?;
+
+        let connection = &context.connection.0;
+        dbmodels::CocktailIngredientI { cocktail_id, ingredient_id }.insert(connection)?;
+
+        Ok(Cocktail::get_by_id(cocktail_id, connection)?)
+    }
+
+    #[graphql(description = "Requires an admin token")]
+    fn remove_cocktail_ingredient(&self, context: &Context, cocktail_id: i32, ingredient_id: i32) -> Result<Cocktail> {
+        context.admin()?;
+
+        let connection = &context.connection.0;
+        dbmodels::CocktailIngredient::delete(cocktail_id, ingredient_id, connection)?;
+
+        Ok(Cocktail::get_by_id(cocktail_id, connection)?)
+    }
+
+    #[graphql(description = "Drinks containing the ingredient are no longer rolled for the user. Logged in users can only exclude ingredients for themselves")]
+    fn exclude_ingredient(&self, context: &Context, user_id: i32, ingredient_id: i32) -> Result<User> {
+        context.token_or_self(user_id)?;
+
+        let connection = &context.connection.0;
+        dbmodels::UserExcludedIngredientI { user_id, ingredient_id }.insert(connection)?;
+
+        Ok(User::get_by_id(user_id, connection)?)
+    }
+
+    #[graphql(description = "Logged in users can only include ingredients for themselves")]
+    fn include_ingredient(&self, context: &Context, user_id: i32, ingredient_id: i32) -> Result<User> {
+        context.token_or_self(user_id)?;
+
+        let connection = &context.connection.0;
+        dbmodels::UserExcludedIngredient::delete(user_id, ingredient_id, connection)?;
+
+        Ok(User::get_by_id(user_id, connection)?)
+    }
+
+    fn revoke_api_token(&self, context: &Context, id: i32) -> Result<dbmodels::ApiToken> {
+        context.admin()?;
+
+        Ok(dbmodels::ApiToken::delete(id, &context.connection.0)?)
+    }
+
+    fn message(&self, context: &Context, message: dbmodels::MessageI) -> Result<Message> {
+        context.token()?;
+
+        let connection = &context.connection.0;
+        let dbmodel = message.insert(connection)?;
+
+        Ok(Message::get_by_id(dbmodel.id, connection)?)
+    }
+}
+
+#[derive(Clone, GraphQLInputObject)]
+pub(crate) struct ChatQuery {
+    pub(crate) id: Option<i32>,
+    pub(crate) chat_type: Option<ChatType>,
+    pub(crate) telegram_id: Option<i32>,
+    pub(crate) title: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) current_keyboard: Option<KeyboardType>,
+    pub(crate) spam_detection: Option<bool>,
+}
+
+#[juniper::object(Context = Context)]
+impl QueryRoot {
+    #[graphql(description = "All fields inside `query` are joined by an OR")]
+    fn chats(&self, context: &Context, constraints: Option<Constraints>, query: Option<ChatQuery>, order_by: Option<Vec<ChatOrder>>) -> Result<Vec<Chat>> {
+        let constraints = constraints.unwrap_or_default();
+        let order_by = order_by.unwrap_or_default();
+
+        // performance optimization, if `query` is undefined, we can just get all chats (limited by `constraints`)
+        let chats = match query {
+            Some(val) => models::Chat::get_by_query(&context.connection.0, constraints, val, &order_by),
+            None => models::Chat::get(&context.connection.0, constraints, &order_by)
+        }?;
+        context.loaders.register_chats(&chats);
+
+        Ok(chats)
+    }
+
+    #[graphql(description = "Relay connection over `chats`, ordered by `orderBy` and then by id")]
+    fn chats_connection(&self, context: &Context, first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, query: Option<ChatQuery>, order_by: Option<Vec<ChatOrder>>) -> Result<ChatConnection> {
+        let connection = &context.connection.0;
+        let order_by = order_by.unwrap_or_default();
+        let page = Page::new(first, after, last, before, models::Chat::count(connection, query.clone())?)?;
+
+        let chats = match query {
+            Some(val) => models::Chat::get_by_query(connection, page.constraints()?, val, &order_by),
+            None => models::Chat::get(connection, page.constraints()?, &order_by)
+        }?;
+        context.loaders.register_chats(&chats);
+
+        ChatConnection::new(chats, page)
+    }
+
+    fn events(&self, context: &Context, constraints: Option<Constraints>, order_by: Option<Vec<EventOrder>>) -> Result<Vec<models::Event>> {
+        let constraints = constraints.unwrap_or_default();
+
+        let events = models::Event::get(&context.connection.0, constraints, &order_by.unwrap_or_default())?;
+        context.loaders.register_events(&events);
+
+        Ok(events)
+    }
+
+    #[graphql(description = "Relay connection over `events`, ordered by `orderBy` and then by id")]
+    fn events_connection(&self, context: &Context, first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, order_by: Option<Vec<EventOrder>>) -> Result<EventConnection> {
+        let connection = &context.connection.0;
+        let page = Page::new(first, after, last, before, models::Event::count(connection)?)?;
+
+        let events = models::Event::get(connection, page.constraints()?, &order_by.unwrap_or_default())?;
+        context.loaders.register_events(&events);
+
+        EventConnection::new(events, page)
+    }
+
+    #[graphql(description = "The user logged in through telegram, if any")]
+    fn me(&self, context: &Context) -> Option<&User> {
+        match &context.principal {
+            auth::Principal::User(user) => Some(user),
+            _ => None,
+        }
+    }
+
+    fn api_tokens(&self, context: &Context, constraints: Option<Constraints>, order_by: Option<Vec<ApiTokenOrder>>) -> Result<Vec<dbmodels::ApiToken>> {
+        context.admin()?;
+        let constraints = constraints.unwrap_or_default();
+
+        Ok(dbmodels::ApiToken::get(&context.connection.0, constraints, &order_by.unwrap_or_default())?)
+    }
+
+    fn users(&self, context: &Context, constraints: Option<Constraints>, order_by: Option<Vec<UserOrder>>) -> Result<Vec<User>> {
+        let constraints = constraints.unwrap_or_default();
+
+        let users = models::User::get(&context.connection.0, constraints, &order_by.unwrap_or_default())?;
+        context.loaders.register_users(&users);
+
+        Ok(users)
+    }
+
+    #[graphql(description = "Relay connection over `users`, ordered by `orderBy` and then by id")]
+    fn users_connection(&self, context: &Context, first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, order_by: Option<Vec<UserOrder>>) -> Result<UserConnection> {
+        let connection = &context.connection.0;
+        let page = Page::new(first, after, last, before, models::User::count(connection)?)?;
+
+        let users = models::User::get(connection, page.constraints()?, &order_by.unwrap_or_default())?;
+        context.loaders.register_users(&users);
+
+        UserConnection::new(users, page)
+    }
+
+    fn user_rolls(&self, context: &Context, constraints: Option<Constraints>, order_by: Option<Vec<UserRollOrder>>) -> Result<Vec<UserRoll>> {
+        let constraints = constraints.unwrap_or_default();
+
+        let rolls: Vec<UserRoll> = models::UserRoll::get(&context.connection.0, constraints, &order_by.unwrap_or_default())?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        context.loaders.register_rolls(&rolls);
+
+        Ok(rolls)
+    }
+
+    #[graphql(description = "Relay connection over `userRolls`, ordered by `orderBy` and then by id")]
+    fn user_rolls_connection(&self, context: &Context, first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, order_by: Option<Vec<UserRollOrder>>) -> Result<UserRollConnection> {
+        let connection = &context.connection.0;
+        let page = Page::new(first, after, last, before, models::UserRoll::count(connection)?)?;
+
+        let rolls = models::UserRoll::get(connection, page.constraints()?, &order_by.unwrap_or_default())?;
+        context.loaders.register_rolls(&rolls);
+
+        UserRollConnection::new(rolls, page)
+    }
+
+    #[graphql(description = "Cocktails of all menus unless `menuId` is given. Retired cocktails are left out unless `includeRetired` is set")]
+    fn cocktails(&self, context: &Context, constraints: Option<Constraints>, filter: Option<CocktailFilter>, order_by: Option<Vec<CocktailOrder>>, menu_id: Option<i32>, include_retired: Option<bool>) -> Result<Vec<Cocktail>> {
+        let constraints = constraints.unwrap_or_default();
+        let filter = filter.unwrap_or_default();
+
+        let cocktails = Cocktail::get(&context.connection.0, constraints, filter, &order_by.unwrap_or_default(), menu_id, include_retired.unwrap_or(false))?;
+        context.loaders.cocktail_ingredients.register(cocktails.iter().map(|cocktail| cocktail.id));
+        context.loaders.cocktail_categories.register(cocktails.iter().map(|cocktail| cocktail.category_id));
+
+        Ok(cocktails)
+    }
+
+    #[graphql(description = "Relay connection over `cocktails`, ordered by `orderBy` and then by id")]
+    fn cocktails_connection(&self, context: &Context, first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, filter: Option<CocktailFilter>, order_by: Option<Vec<CocktailOrder>>, menu_id: Option<i32>, include_retired: Option<bool>) -> Result<CocktailConnection> {
+        let connection = &context.connection.0;
+        let filter = filter.unwrap_or_default();
+        let include_retired = include_retired.unwrap_or(false);
+        let page = Page::new(first, after, last, before, dbmodels::Cocktail::count(connection, filter.clone(), menu_id, include_retired)?)?;
+
+        let cocktails = Cocktail::get(connection, page.constraints()?, filter, &order_by.unwrap_or_default(), menu_id, include_retired)?;
+        context.loaders.cocktail_ingredients.register(cocktails.iter().map(|cocktail| cocktail.id));
+        context.loaders.cocktail_categories.register(cocktails.iter().map(|cocktail| cocktail.category_id));
+
+        CocktailConnection::new(cocktails, page)
+    }
+
+    #[graphql(description = "Ordered by position")]
+    fn cocktail_categories(&self, context: &Context) -> Result<Vec<CocktailCategory>> {
+        Ok(CocktailCategory::get(&context.connection.0)?)
+    }
+
+    fn menus(&self, context: &Context, constraints: Option<Constraints>, order_by: Option<Vec<MenuOrder>>) -> Result<Vec<Menu>> {
+        let constraints = constraints.unwrap_or_default();
+
+        Ok(dbmodels::Menu::get(&context.connection.0, constraints, &order_by.unwrap_or_default())?)
+    }
+
+    fn ingredients(&self, context: &Context, constraints: Option<Constraints>, order_by: Option<Vec<IngredientOrder>>) -> Result<Vec<dbmodels::Ingredient>> {
+        let constraints = constraints.unwrap_or_default();
+
+        Ok(dbmodels::Ingredient::get(&context.connection.0, constraints, &order_by.unwrap_or_default())?)
+    }
+
+    #[graphql(description = "All filters are joined by an AND, `from` and `to` are inclusive. Ordered by timestamp unless `orderBy` is given")]
+    fn messages(&self, context: &Context, chat_id: Option<i32>, user_id: Option<i32>, from: Option<DateTime<FixedOffset>>, to: Option<DateTime<FixedOffset>>, constraints: Option<Constraints>, order_by: Option<Vec<MessageOrder>>) -> Result<Vec<Message>> {
+        let constraints = constraints.unwrap_or_default();
+
+        let messages = Message::get(&context.connection.0, constraints, &order_by.unwrap_or_default(), chat_id, user_id, from, to)?;
+        context.loaders.register_messages(&messages);
+
+        Ok(messages)
+    }
+
+    #[graphql(description = "Relay connection over `messages`, ordered by timestamp unless `orderBy` is given")]
+    fn messages_connection(&self, context: &Context, first: Option<i32>, after: Option<String>, last: Option<i32>, before: Option<String>, chat_id: Option<i32>, user_id: Option<i32>, from: Option<DateTime<FixedOffset>>, to: Option<DateTime<FixedOffset>>, order_by: Option<Vec<MessageOrder>>) -> Result<MessageConnection> {
+        let connection = &context.connection.0;
+        let page = Page::new(first, after, last, before, Message::count(connection, chat_id, user_id, from, to)?)?;
+
+        let messages = Message::get(connection, page.constraints()?, &order_by.unwrap_or_default(), chat_id, user_id, from, to)?;
+        context.loaders.register_messages(&messages);
+
+        MessageConnection::new(messages, page)
+    }
+
+    #[graphql(description = "Statistics over the rolls and attendance of a user, limited to events of `chatId` between `from` and `to` (inclusive) if given")]
+    fn user_stats(&self, context: &Context, user_id: i32, chat_id: Option<i32>, from: Option<DateTime<FixedOffset>>, to: Option<DateTime<FixedOffset>>) -> Result<UserStats> {
+        Ok(UserStats::get(user_id, StatsFilter { chat_id, from, to }, &context.connection.0)?)
+    }
+
+    #[graphql(description = "Attendees of the event with their rolls and their share of the bill. Uses the bill rule of the chat unless `rule` is given")]
+    fn event_summary(&self, context: &Context, event_id: i32, rule: Option<models::BillRule>) -> Result<EventSummary> {
+        Ok(EventSummary::get(event_id, rule, &context.connection.0)?)
     }
 
-    fn cocktails(&self, context: &Context, constraints: Option<Constraints>) -> FieldResult<Vec<Cocktail>> {
+    #[graphql(description = "Ranks the users of a chat, only events which already started are taken into account")]
+    fn leaderboard(&self, context: &Context, chat_id: i32, metric: LeaderboardMetric, period: LeaderboardPeriod, constraints: Option<Constraints>) -> Result<Vec<LeaderboardEntry>> {
         let constraints = constraints.unwrap_or_default();
 
-        Ok(Cocktail::get(&context.connection.0, constraints)?)
+        Ok(LeaderboardEntry::get(chat_id, metric, period, constraints, &context.connection.0)?)
     }
 }
@@ -0,0 +1,109 @@
+use chrono_tz::Tz;
+use diesel::OptionalExtension;
+
+use crate::database::models as dbmodels;
+use crate::error::{Error, Result, Violations};
+use crate::graphql::schema::{InputChat, InputEvent, InputUser};
+use crate::models::{Cocktail, DICE_FACES, EventUser};
+
+/// Checks an input before anything is written, reporting every invalid field at once.
+///
+/// Validations may read from the database (e.g. to check that referenced rows exist), but never write to it.
+pub trait Validate {
+    fn validate(&self, connection: &diesel::PgConnection) -> Result<()>;
+}
+
+/// A patch which may leave out every field.
+///
+/// Diesel refuses to build an empty changeset, so patches are checked before they are applied.
+pub trait Patch {
+    fn is_empty(&self) -> bool;
+
+    fn require_changes(&self) -> Result<()> {
+        if self.is_empty() {
+            return Err(Error::validation("EMPTY_PATCH", "The patch doesn't change anything"));
+        }
+
+        Ok(())
+    }
+}
+
+macro_rules! impl_patch {
+    ($($patch:ty { $($field:ident),+ })+) => {
+        $(
+            impl Patch for $patch {
+                fn is_empty(&self) -> bool {
+                    $(self.$field.is_none())&&+
+                }
+            }
+        )+
+    };
+}
+
+impl_patch! {
+    dbmodels::ChatPatch { chat_type, title, description, current_keyboard, spam_detection, timezone, bill_rule, menu_id }
+    dbmodels::ChatUserPatch { spamming, muted, admin }
+    dbmodels::EventPatch { timestamp, menu_id }
+    dbmodels::EventSchedulePatch { weekday, time, timezone }
+    dbmodels::EventUserPatch { attends }
+    dbmodels::UserRollPatch { jumbo, alcoholic, roll, drink }
+    dbmodels::UserPatch { username, first_name, last_name, is_bot, has_private_conversation, non_alcoholic }
+    dbmodels::CocktailPatch { menu_id, name, jumbo, alcoholic, category, price, jumbo_price, retired }
+}
+
+fn is_blank(value: &str) -> bool {
+    value.trim().is_empty()
+}
+
+impl Validate for InputUser {
+    fn validate(&self, _: &diesel::PgConnection) -> Result<()> {
+        let mut violations = Violations::new();
+        violations.check(!is_blank(&self.first_name), "firstName", "must not be empty");
+        violations.check(self.username.as_ref().map_or(true, |username| !is_blank(username)), "username", "must not be empty, leave it out instead");
+        violations.check(self.telegram_id > 0, "telegramId", "has to be positive");
+
+        violations.into_result()
+    }
+}
+
+impl Validate for InputChat {
+    fn validate(&self, _: &diesel::PgConnection) -> Result<()> {
+        let mut violations = Violations::new();
+        violations.check(!is_blank(&self.title), "title", "must not be empty");
+        violations.check(self.timezone.as_ref().map_or(true, |timezone| timezone.parse::<Tz>().is_ok()), "timezone", "has to be an IANA name like `Europe/Berlin`");
+
+        violations.into_result()
+    }
+}
+
+impl Validate for InputEvent {
+    fn validate(&self, connection: &diesel::PgConnection) -> Result<()> {
+        let mut violations = Violations::new();
+        violations.check(dbmodels::Chat::get_by_id(self.chat_id, connection).optional()?.is_some(), "chatId", "doesn't exist");
+        if let Some(menu_id) = self.menu_id {
+            violations.check(dbmodels::Menu::get_by_ids(&[menu_id], connection)?.contains_key(&menu_id), "menuId", "doesn't exist");
+        }
+
+        violations.into_result()
+    }
+}
+
+impl Validate for dbmodels::UserRollI {
+    fn validate(&self, connection: &diesel::PgConnection) -> Result<()> {
+        let mut violations = Violations::new();
+        violations.check(!is_blank(&self.callback_query_id), "callbackQueryId", "must not be empty");
+        violations.check(self.roll >= 1 && self.roll <= DICE_FACES, "roll", format!("has to be between 1 and {}", DICE_FACES));
+
+        match EventUser::get_by_id(self.event_user_id, connection).optional()? {
+            // Drinks are only known within the menu of the event
+            Some(event_user) => violations.check(
+                Cocktail::get_by_name(event_user.event.effective_menu_id(), &self.drink, connection)?.is_some(),
+                "drink",
+                "is not on the menu of the event",
+            ),
+            None => violations.check(false, "eventUserId", "doesn't exist"),
+        }
+
+        violations.into_result()
+    }
+}
@@ -1,9 +1,14 @@
 #![feature(decl_macro, proc_macro_hygiene)]
 extern crate chrono;
+extern crate chrono_tz;
 #[macro_use]
 extern crate diesel;
+extern crate hex;
+extern crate hmac;
 #[macro_use]
 extern crate juniper;
+#[macro_use]
+extern crate log;
 extern crate r2d2;
 extern crate r2d2_diesel;
 extern crate rand;
@@ -11,9 +16,16 @@ extern crate rocket;
 #[macro_use]
 extern crate rocket_contrib;
 extern crate rocket_http;
+extern crate serde_json;
+extern crate sha2;
 
+pub mod auth;
+pub mod bill;
 pub mod database;
+pub mod error;
 pub mod models;
 pub mod graphql;
 pub mod routes;
+pub mod scheduler;
+pub mod stats;
 pub mod db;
@@ -1,110 +1,190 @@
-use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::ops::Range;
+
+use chrono::{DateTime, FixedOffset, NaiveTime, Offset, Utc};
+use chrono_tz::Tz;
+use diesel::dsl::any;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::SmallInt;
+use rand::Rng;
 
 use crate::database::models as db_models;
 use crate::database::models::Ingredient;
 use crate::database::schema;
-use crate::graphql::schema::{ChatQuery, Constraints};
+use crate::error;
+use crate::graphql::Context;
+use crate::graphql::order::{ChatOrder, ChatOrderField, CocktailOrder, EventOrder, EventOrderField, MessageOrder, MessageOrderField, UserOrder, UserOrderField, UserRollOrder, UserRollOrderField};
+use crate::graphql::schema::{ChatQuery, CocktailFilter, Constraints};
+use crate::then_order_by;
 
 type DieselResult<T> = Result<T, diesel::result::Error>;
 
-#[derive(Debug, GraphQLEnum)]
-pub(crate) enum CocktailCategory {
-    CAIPI = 0,
-    JUMBO,
-    VODKA,
-    COLADAS,
-    RUM,
-    TEQUILA,
-    FROZEN_MARGARITAS,
-    GIN,
+/// Number of faces on the dice used to pick a drink.
+pub(crate) const DICE_FACES: i32 = 6;
+
+/// A stored value which doesn't map to any variant of the enum it is read into.
+#[derive(Debug)]
+pub struct UnknownVariant {
+    pub(crate) enum_name: &'static str,
+    pub(crate) value: i16,
 }
 
-impl From<i16> for CocktailCategory {
-    fn from(input: i16) -> Self {
-        match input {
-            0 => CocktailCategory::CAIPI,
-            1 => CocktailCategory::JUMBO,
-            2 => CocktailCategory::VODKA,
-            3 => CocktailCategory::COLADAS,
-            4 => CocktailCategory::RUM,
-            5 => CocktailCategory::TEQUILA,
-            6 => CocktailCategory::FROZEN_MARGARITAS,
-            7 => CocktailCategory::GIN,
-            _ => unimplemented!(),
-        }
+impl fmt::Display for UnknownVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown {} value {}", self.enum_name, self.value)
     }
 }
 
-impl ToString for CocktailCategory {
-    fn to_string(&self) -> String {
-        match self {
-            CocktailCategory::CAIPI => "Caipi",
-            CocktailCategory::JUMBO => "Jumbo",
-            CocktailCategory::VODKA => "Vodka",
-            CocktailCategory::COLADAS => "Coladas",
-            CocktailCategory::RUM => "Rum",
-            CocktailCategory::TEQUILA => "Tequila",
-            CocktailCategory::FROZEN_MARGARITAS => "Frozen Margaritas",
-            CocktailCategory::GIN => "Gin",
-        }.to_string()
-    }
+impl Error for UnknownVariant {}
+
+/// Maps an enum onto a `SMALLINT` column. Values without a variant fail to load with `UnknownVariant`
+/// instead of panicking, so the request fails with an error rather than taking the worker down.
+macro_rules! small_int_enum {
+    ($name:ident { $($value:literal => $variant:ident),+ $(,)? }) => {
+        impl TryFrom<i16> for $name {
+            type Error = UnknownVariant;
+
+            fn try_from(input: i16) -> Result<$name, UnknownVariant> {
+                match input {
+                    $($value => Ok($name::$variant),)+
+                    value => Err(UnknownVariant { enum_name: stringify!($name), value }),
+                }
+            }
+        }
+
+        impl From<$name> for i16 {
+            fn from(input: $name) -> i16 {
+                input as i16
+            }
+        }
+
+        impl ToSql<SmallInt, Pg> for $name {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+                ToSql::<SmallInt, Pg>::to_sql(&i16::from(*self), out)
+            }
+        }
+
+        impl FromSql<SmallInt, Pg> for $name {
+            fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<$name> {
+                let value: i16 = FromSql::<SmallInt, Pg>::from_sql(bytes)?;
+
+                Ok($name::try_from(value)?)
+            }
+        }
+    };
 }
 
-#[derive(Debug, GraphQLEnum)]
+#[derive(AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, GraphQLEnum, PartialEq)]
+#[sql_type = "SmallInt"]
 pub enum ChatType {
     PRIVATE = 0,
     GROUP,
     SUPERGROUP,
 }
 
-impl From<i16> for ChatType {
-    fn from(input: i16) -> ChatType {
-        match input {
-            0 => ChatType::PRIVATE,
-            1 => ChatType::GROUP,
-            2 => ChatType::SUPERGROUP,
-            _ => unimplemented!()
-        }
-    }
+small_int_enum!(ChatType {
+    0 => PRIVATE,
+    1 => GROUP,
+    2 => SUPERGROUP,
+});
+
+/// Decides who pays for the drinks of an event.
+#[derive(AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, GraphQLEnum, PartialEq)]
+#[sql_type = "SmallInt"]
+pub enum BillRule {
+    #[graphql(description = "Everyone pays their own drinks")]
+    OWN_DRINKS = 0,
+    #[graphql(description = "Whoever rolled the lowest value pays for everyone, ties split the bill")]
+    LOWEST_ROLL_PAYS,
+    #[graphql(description = "The bill is split evenly between all attendees")]
+    SPLIT_EVENLY,
 }
 
-#[derive(Debug, GraphQLEnum)]
+small_int_enum!(BillRule {
+    0 => OWN_DRINKS,
+    1 => LOWEST_ROLL_PAYS,
+    2 => SPLIT_EVENLY,
+});
+
+#[derive(AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, GraphQLEnum, PartialEq)]
+#[sql_type = "SmallInt"]
 pub enum KeyboardType {
     NONE = 0,
     ATTEND,
     DICE,
 }
 
-#[derive(Debug, GraphQLObject)]
+small_int_enum!(KeyboardType {
+    0 => NONE,
+    1 => ATTEND,
+    2 => DICE,
+});
+
+#[derive(AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, GraphQLEnum, PartialEq)]
+#[sql_type = "SmallInt"]
+pub enum Weekday {
+    MONDAY = 0,
+    TUESDAY,
+    WEDNESDAY,
+    THURSDAY,
+    FRIDAY,
+    SATURDAY,
+    SUNDAY,
+}
+
+small_int_enum!(Weekday {
+    0 => MONDAY,
+    1 => TUESDAY,
+    2 => WEDNESDAY,
+    3 => THURSDAY,
+    4 => FRIDAY,
+    5 => SATURDAY,
+    6 => SUNDAY,
+});
+
+impl From<Weekday> for chrono::Weekday {
+    fn from(input: Weekday) -> chrono::Weekday {
+        match input {
+            Weekday::MONDAY => chrono::Weekday::Mon,
+            Weekday::TUESDAY => chrono::Weekday::Tue,
+            Weekday::WEDNESDAY => chrono::Weekday::Wed,
+            Weekday::THURSDAY => chrono::Weekday::Thu,
+            Weekday::FRIDAY => chrono::Weekday::Fri,
+            Weekday::SATURDAY => chrono::Weekday::Sat,
+            Weekday::SUNDAY => chrono::Weekday::Sun,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub(crate) struct Cocktail {
     pub(crate) id: i32,
     pub(crate) name: String,
     pub(crate) jumbo: bool,
     pub(crate) alcoholic: bool,
-    pub(crate) category: String,
-    pub(crate) ingredients: Vec<db_models::Ingredient>,
+    pub(crate) category_id: i32,
+    pub(crate) price: Option<i32>,
+    pub(crate) jumbo_price: Option<i32>,
+    pub(crate) retired: bool,
+    pub(crate) menu_id: i32,
 }
 
 #[derive(Debug, GraphQLObject)]
+#[graphql(Context = Context)]
 pub struct CocktailIngredient {
     pub(crate) id: i32,
     pub(crate) cocktail: Cocktail,
     pub(crate) ingredient: db_models::Ingredient,
 }
 
-impl From<i16> for KeyboardType {
-    fn from(input: i16) -> KeyboardType {
-        match input {
-            0 => KeyboardType::NONE,
-            1 => KeyboardType::ATTEND,
-            2 => KeyboardType::DICE,
-            _ => unimplemented!()
-        }
-    }
-}
-
-#[derive(Debug, GraphQLObject)]
+#[derive(Clone, Debug)]
 pub struct Chat {
     pub id: i32,
     pub chat_type: ChatType,
@@ -113,18 +193,24 @@ pub struct Chat {
     pub description: Option<String>,
     pub current_keyboard: KeyboardType,
     pub spam_detection: bool,
+    pub timezone: String,
+    pub bill_rule: BillRule,
+    pub menu_id: i32,
 }
 
 impl From<Chat> for db_models::Chat {
     fn from(input: Chat) -> Self {
         db_models::Chat {
             id: input.id,
-            chat_type: input.chat_type as i16,
+            chat_type: input.chat_type,
             telegram_id: input.telegram_id,
             title: input.title.clone(),
             description: input.description,
-            current_keyboard: input.current_keyboard as i16,
+            current_keyboard: input.current_keyboard,
             spam_detection: false,
+            timezone: input.timezone,
+            bill_rule: input.bill_rule,
+            menu_id: input.menu_id,
         }
     }
 }
@@ -134,13 +220,15 @@ impl From<Event> for db_models::Event {
         db_models::Event {
             id: input.id,
             chat_id: input.chat.id,
-            timestamp: input.timestamp,
+            timestamp: input.timestamp.with_timezone(&Utc),
             active: input.active,
+            menu_id: input.menu_id,
         }
     }
 }
 
-#[derive(Debug, GraphQLObject)]
+#[derive(Clone, Debug, GraphQLObject)]
+#[graphql(Context = Context)]
 pub struct ChatUser {
     pub(crate) id: i32,
     pub(crate) user: User,
@@ -150,15 +238,19 @@ pub struct ChatUser {
     pub(crate) admin: bool,
 }
 
-#[derive(Debug, GraphQLObject)]
+#[derive(Clone, Debug)]
 pub struct Event {
     pub id: i32,
     pub chat: Chat,
-    pub timestamp: NaiveDateTime,
+    /// In the timezone of the chat
+    pub timestamp: DateTime<FixedOffset>,
     pub active: bool,
+    /// Overrides the menu of the chat
+    pub menu_id: Option<i32>,
 }
 
-#[derive(Debug, GraphQLObject)]
+#[derive(Clone, Debug, GraphQLObject)]
+#[graphql(Context = Context)]
 pub struct EventUser {
     pub(crate) id: i32,
     pub(crate) event: Event,
@@ -166,7 +258,7 @@ pub struct EventUser {
     pub(crate) attends: bool,
 }
 
-#[derive(Debug, GraphQLObject)]
+#[derive(Clone, Debug)]
 pub struct UserRoll {
     pub(crate) id: i32,
     pub(crate) event_user: EventUser,
@@ -176,38 +268,68 @@ pub struct UserRoll {
     pub(crate) drink: String,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Message {
-    id: i32,
-    telegram_id: i32,
-    chat: Chat,
-    user: Option<User>,
-    timestamp: NaiveDateTime,
-    //    reply_to: Option<Rc<RefCell<Message>>>,
-    reply_to_message_id: Option<i32>,
-    edit_timestamp: Option<NaiveDateTime>,
-    text: Option<String>,
-    caption: Option<String>,
-    new_chat_members: Vec<User>,
-    left_chat_member: Option<User>,
-    new_chat_title: Option<String>,
-    group_chat_created: Option<bool>,
-    supergroup_chat_created: Option<bool>,
-    migrate_to_chat: Option<Chat>,
-    migrate_from_chat: Option<Chat>,
-    //    pinned_message: Option<Rc<RefCell<Message>>>,
-    pinned_message_id: Option<i32>,
+    pub(crate) id: i32,
+    pub(crate) telegram_id: i32,
+    pub(crate) chat: Chat,
+    pub(crate) user: Option<User>,
+    /// In the timezone of the chat
+    pub(crate) timestamp: DateTime<FixedOffset>,
+    pub(crate) reply_to_message_id: Option<i32>,
+    pub(crate) edit_timestamp: Option<DateTime<FixedOffset>>,
+    pub(crate) text: Option<String>,
+    pub(crate) caption: Option<String>,
+    pub(crate) new_chat_member_ids: Vec<i32>,
+    pub(crate) left_chat_member_id: Option<i32>,
+    pub(crate) new_chat_title: Option<String>,
+    pub(crate) group_chat_created: Option<bool>,
+    pub(crate) supergroup_chat_created: Option<bool>,
+    pub(crate) migrate_to_chat_id: Option<i32>,
+    pub(crate) migrate_from_chat_id: Option<i32>,
+    pub(crate) pinned_message_id: Option<i32>,
 }
 
 pub type User = db_models::User;
 
+pub type CocktailCategory = db_models::CocktailCategory;
+
+pub type Menu = db_models::Menu;
+
 impl Chat {
-    pub(crate) fn get_by_query(connection: &diesel::PgConnection, constraints: Constraints, query: ChatQuery) -> DieselResult<Vec<Chat>> {
-        use schema::chats::dsl::*;
-        let mut sqlquery = schema::chats::table
+    /// Converts a timestamp stored as UTC into the timezone of the chat.
+    pub(crate) fn localize(&self, timestamp: DateTime<Utc>) -> DateTime<FixedOffset> {
+        // Timezones are validated when they are set, UTC is only a fallback
+        let timezone: Tz = self.timezone.parse().unwrap_or(Tz::UTC);
+        let local = timestamp.with_timezone(&timezone);
+
+        local.with_timezone(&local.offset().fix())
+    }
+
+    pub(crate) fn get_by_query(connection: &diesel::PgConnection, constraints: Constraints, query: ChatQuery, order_by: &[ChatOrder]) -> DieselResult<Vec<Chat>> {
+        Ok(Chat::ordered(Chat::filter_by_query(query), order_by)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
-            .into_boxed();
+            .load::<db_models::Chat>(connection)?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Number of chats matching `query`, or of all chats if there is none.
+    pub(crate) fn count(connection: &diesel::PgConnection, query: Option<ChatQuery>) -> DieselResult<i64> {
+        let sqlquery = match query {
+            Some(query) => Chat::filter_by_query(query),
+            None => schema::chats::table.into_boxed(),
+        };
+
+        sqlquery.count().get_result(connection)
+    }
+
+    /// All fields of `query` are joined by an OR.
+    fn filter_by_query(query: ChatQuery) -> schema::chats::BoxedQuery<'static, Pg> {
+        use schema::chats::dsl::*;
+        let mut sqlquery = schema::chats::table.into_boxed();
 
         if let Some(val) = query.title {
             sqlquery = sqlquery.or_filter(title.eq(val));
@@ -219,29 +341,25 @@ impl Chat {
             sqlquery = sqlquery.or_filter(id.eq(val));
         }
         if let Some(val) = query.chat_type {
-            let val = val as i16;
+            let val = ChatType::from(val);
             sqlquery = sqlquery.or_filter(chat_type.eq(val));
         }
         if let Some(val) = query.telegram_id {
             sqlquery = sqlquery.or_filter(telegram_id.eq(val));
         }
         if let Some(val) = query.current_keyboard {
-            let val = val as i16;
+            let val = KeyboardType::from(val);
             sqlquery = sqlquery.or_filter(current_keyboard.eq(val));
         }
         if let Some(val) = query.spam_detection {
             sqlquery = sqlquery.or_filter(spam_detection.eq(val));
         }
 
-        Ok(sqlquery
-            .load::<db_models::Chat>(connection)?
-            .into_iter()
-            .map(Into::into)
-            .collect())
+        sqlquery
     }
 
-    pub fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<Chat>> {
-        Ok(schema::chats::table
+    pub fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[ChatOrder]) -> DieselResult<Vec<Chat>> {
+        Ok(Chat::ordered(schema::chats::table.into_boxed(), order_by)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
             .load::<db_models::Chat>(connection)?
@@ -250,23 +368,48 @@ impl Chat {
             .collect())
     }
 
+    /// Sorts by `order_by`, ties are broken by the id.
+    fn ordered(mut query: schema::chats::BoxedQuery<'static, Pg>, order_by: &[ChatOrder]) -> schema::chats::BoxedQuery<'static, Pg> {
+        use schema::chats::dsl;
+
+        for order in order_by {
+            query = match order.field {
+                ChatOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                ChatOrderField::TITLE => then_order_by!(query, dsl::title, order.direction()),
+                ChatOrderField::TELEGRAM_ID => then_order_by!(query, dsl::telegram_id, order.direction()),
+            };
+        }
+
+        query.then_order_by(dsl::id)
+    }
+
     pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Chat> {
         Ok(schema::chats::table
             .find(id)
             .first::<db_models::Chat>(connection)?
             .into())
     }
+
+    pub(crate) fn get_by_ids(ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Chat>> {
+        Ok(schema::chats::table
+            .filter(schema::chats::dsl::id.eq(any(ids.to_vec())))
+            .load::<db_models::Chat>(connection)?
+            .into_iter()
+            .map(|chat| (chat.id, chat.into()))
+            .collect())
+    }
 }
 
 impl Into<db_models::ChatI> for Chat {
     fn into(self) -> db_models::ChatI {
         db_models::ChatI {
-            chat_type: self.chat_type as i16,
+            chat_type: self.chat_type,
             telegram_id: self.telegram_id,
             title: self.title,
             description: self.description,
-            current_keyboard: self.current_keyboard as i16,
+            current_keyboard: self.current_keyboard,
             spam_detection: self.spam_detection,
+            timezone: Some(self.timezone),
         }
     }
 }
@@ -275,12 +418,15 @@ impl Into<Chat> for db_models::Chat {
     fn into(self: db_models::Chat) -> Chat {
         Chat {
             id: self.id,
-            chat_type: ChatType::from(self.chat_type as i16),
+            chat_type: self.chat_type,
             telegram_id: self.telegram_id,
             title: self.title,
             description: self.description,
-            current_keyboard: KeyboardType::from(self.current_keyboard),
+            current_keyboard: self.current_keyboard,
             spam_detection: self.spam_detection,
+            timezone: self.timezone,
+            bill_rule: self.bill_rule,
+            menu_id: self.menu_id,
         }
     }
 }
@@ -315,23 +461,44 @@ impl ChatUser {
             ))
     }
 
-    pub fn get_by_chat_id(chat_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<ChatUser>> {
-        Ok(
-            ChatUser::from_loaded_vec(schema::chat_users::table
-                .filter(schema::chat_users::dsl::chat_id.eq(chat_id))
-                .inner_join(schema::chats::table)
-                .inner_join(schema::users::table)
-                .load(connection)?
-            ))
+    /// Members keyed by their chat id.
+    pub(crate) fn get_by_chat_ids(chat_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<ChatUser>>> {
+        let mut chat_users: HashMap<i32, Vec<ChatUser>> = HashMap::new();
+        for chat_user in ChatUser::from_loaded_vec(schema::chat_users::table
+            .filter(schema::chat_users::dsl::chat_id.eq(any(chat_ids.to_vec())))
+            .inner_join(schema::chats::table)
+            .inner_join(schema::users::table)
+            .order(schema::chat_users::dsl::id)
+            .load(connection)?) {
+            chat_users.entry(chat_user.chat.id).or_default().push(chat_user);
+        }
+
+        Ok(chat_users)
     }
 
-    pub fn get_by_user_id(user_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<ChatUser>> {
-        Ok(
-            ChatUser::from_loaded_vec(schema::chat_users::table
-                .filter(schema::chat_users::dsl::user_id.eq(user_id))
-                .inner_join(schema::chats::table)
-                .inner_join(schema::users::table)
-                .load(connection)?))
+    /// Memberships keyed by their user id.
+    pub(crate) fn get_by_user_ids(user_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<ChatUser>>> {
+        let mut chat_users: HashMap<i32, Vec<ChatUser>> = HashMap::new();
+        for chat_user in ChatUser::from_loaded_vec(schema::chat_users::table
+            .filter(schema::chat_users::dsl::user_id.eq(any(user_ids.to_vec())))
+            .inner_join(schema::chats::table)
+            .inner_join(schema::users::table)
+            .order(schema::chat_users::dsl::id)
+            .load(connection)?) {
+            chat_users.entry(chat_user.user.id).or_default().push(chat_user);
+        }
+
+        Ok(chat_users)
+    }
+
+    pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<ChatUser> {
+        let (chat_user, chat, user) = schema::chat_users::table
+            .filter(schema::chat_users::dsl::id.eq(id))
+            .inner_join(schema::chats::table)
+            .inner_join(schema::users::table)
+            .first(connection)?;
+
+        Ok(ChatUser::from_database_model(chat_user, chat, user))
     }
 }
 
@@ -349,17 +516,38 @@ impl Into<db_models::ChatUserI> for ChatUser {
 
 impl Event {
     fn from_database_model((event, chat): (db_models::Event, db_models::Chat)) -> Event {
+        let chat: Chat = chat.into();
+
         Event {
             id: event.id,
-            chat: chat.into(),
-            timestamp: event.timestamp,
+            timestamp: chat.localize(event.timestamp),
+            chat,
             active: event.active,
+            menu_id: event.menu_id,
         }
     }
 
-    pub fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<Event>> {
-        Ok(schema::events::table
+    /// The menu drinks of the event are picked from.
+    pub fn effective_menu_id(&self) -> i32 {
+        self.menu_id.unwrap_or(self.chat.menu_id)
+    }
+
+    /// Sorted by `order_by`, ties are broken by the id.
+    pub fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[EventOrder]) -> DieselResult<Vec<Event>> {
+        use schema::events::dsl;
+
+        let mut query = dsl::events
             .inner_join(schema::chats::table)
+            .into_boxed();
+        for order in order_by {
+            query = match order.field {
+                EventOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                EventOrderField::TIMESTAMP => then_order_by!(query, dsl::timestamp, order.direction()),
+            };
+        }
+
+        Ok(query
+            .then_order_by(dsl::id)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
             .load(connection)?
@@ -368,6 +556,38 @@ impl Event {
             .collect())
     }
 
+    pub(crate) fn count(connection: &diesel::PgConnection) -> DieselResult<i64> {
+        schema::events::table
+            .count()
+            .get_result(connection)
+    }
+
+    pub(crate) fn get_by_chat_ids(chat_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<Event>>> {
+        let mut events: HashMap<i32, Vec<Event>> = HashMap::new();
+        for event in schema::events::table
+            .filter(schema::events::dsl::chat_id.eq(any(chat_ids.to_vec())))
+            .inner_join(schema::chats::table)
+            .order(schema::events::dsl::timestamp)
+            .load(connection)?
+            .into_iter()
+            .map(Event::from_database_model) {
+            events.entry(event.chat.id).or_default().push(event);
+        }
+
+        Ok(events)
+    }
+
+    pub fn get_by_chat_id(chat_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<Event>> {
+        Ok(schema::events::table
+            .filter(schema::events::dsl::chat_id.eq(chat_id))
+            .inner_join(schema::chats::table)
+            .order(schema::events::dsl::timestamp)
+            .load(connection)?
+            .into_iter()
+            .map(Event::from_database_model)
+            .collect())
+    }
+
     pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Event> {
         schema::events::table
             .filter(schema::events::dsl::id.eq(id))
@@ -379,12 +599,58 @@ impl Event {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct EventSchedule {
+    pub id: i32,
+    pub chat: Chat,
+    pub weekday: Weekday,
+    pub time: NaiveTime,
+    pub timezone: String,
+}
+
+impl EventSchedule {
+    fn from_database_model((schedule, chat): (db_models::EventSchedule, db_models::Chat)) -> EventSchedule {
+        EventSchedule {
+            id: schedule.id,
+            chat: chat.into(),
+            weekday: schedule.weekday,
+            time: schedule.time,
+            timezone: schedule.timezone,
+        }
+    }
+
+    pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<EventSchedule> {
+        schema::event_schedules::table
+            .filter(schema::event_schedules::dsl::id.eq(id))
+            .inner_join(schema::chats::table)
+            .first(connection)
+            .map(EventSchedule::from_database_model)
+    }
+
+    /// Schedules keyed by their chat id.
+    pub(crate) fn get_by_chat_ids(chat_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<EventSchedule>>> {
+        let mut schedules: HashMap<i32, Vec<EventSchedule>> = HashMap::new();
+        for schedule in schema::event_schedules::table
+            .filter(schema::event_schedules::dsl::chat_id.eq(any(chat_ids.to_vec())))
+            .inner_join(schema::chats::table)
+            .order((schema::event_schedules::dsl::weekday, schema::event_schedules::dsl::time))
+            .load(connection)?
+            .into_iter()
+            .map(EventSchedule::from_database_model) {
+            schedules.entry(schedule.chat.id).or_default().push(schedule);
+        }
+
+        Ok(schedules)
+    }
+}
+
 impl Into<db_models::EventI> for Event {
     fn into(self) -> db_models::EventI {
         db_models::EventI {
             chat_id: self.chat.id,
-            timestamp: self.timestamp,
+            timestamp: self.timestamp.with_timezone(&Utc),
             active: self.active,
+            menu_id: self.menu_id,
         }
     }
 }
@@ -398,12 +664,7 @@ impl EventUser {
     pub fn from_database_model(event_user: db_models::EventUser, event: db_models::Event, chat: db_models::Chat, user: db_models::User) -> EventUser {
         EventUser {
             id: event_user.id,
-            event: Event {
-                id: event.id,
-                chat: chat.into(),
-                timestamp: event.timestamp,
-                active: event.active,
-            },
+            event: Event::from_database_model((event, chat)),
             user: user.into(),
             attends: event_user.attends,
         }
@@ -424,6 +685,63 @@ impl EventUser {
             .collect())
     }
 
+    pub fn get_by_event_id(event_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<EventUser>> {
+        Ok(schema::event_users::table
+            .filter(schema::event_users::dsl::event_id.eq(event_id))
+            .inner_join(schema::events::table
+                .inner_join(schema::chats::table)
+            )
+            .inner_join(schema::users::table)
+            .load(connection)?
+            .into_iter()
+            .map(|(event_user, (event, chat), user): (db_models::EventUser, (db_models::Event, db_models::Chat), db_models::User)|
+                EventUser::from_database_model(event_user, event, chat, user)
+            )
+            .collect())
+    }
+
+    /// Invitations keyed by their event id.
+    pub(crate) fn get_by_event_ids(event_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<EventUser>>> {
+        let mut event_users: HashMap<i32, Vec<EventUser>> = HashMap::new();
+        for event_user in schema::event_users::table
+            .filter(schema::event_users::dsl::event_id.eq(any(event_ids.to_vec())))
+            .inner_join(schema::events::table
+                .inner_join(schema::chats::table)
+            )
+            .inner_join(schema::users::table)
+            .order(schema::event_users::dsl::id)
+            .load(connection)?
+            .into_iter()
+            .map(|(event_user, (event, chat), user): (db_models::EventUser, (db_models::Event, db_models::Chat), db_models::User)|
+                EventUser::from_database_model(event_user, event, chat, user)
+            ) {
+            event_users.entry(event_user.event.id).or_default().push(event_user);
+        }
+
+        Ok(event_users)
+    }
+
+    /// Invitations keyed by their user id.
+    pub(crate) fn get_by_user_ids(user_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<EventUser>>> {
+        let mut event_users: HashMap<i32, Vec<EventUser>> = HashMap::new();
+        for event_user in schema::event_users::table
+            .filter(schema::event_users::dsl::user_id.eq(any(user_ids.to_vec())))
+            .inner_join(schema::events::table
+                .inner_join(schema::chats::table)
+            )
+            .inner_join(schema::users::table)
+            .order(schema::event_users::dsl::id)
+            .load(connection)?
+            .into_iter()
+            .map(|(event_user, (event, chat), user): (db_models::EventUser, (db_models::Event, db_models::Chat), db_models::User)|
+                EventUser::from_database_model(event_user, event, chat, user)
+            ) {
+            event_users.entry(event_user.user.id).or_default().push(event_user);
+        }
+
+        Ok(event_users)
+    }
+
     pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<EventUser> {
         let (event_user, (event, chat), user): (db_models::EventUser, (db_models::Event, db_models::Chat), db_models::User) =
             filter_eq!(schema::event_users::table, schema::event_users::id, id)
@@ -447,80 +765,177 @@ impl Into<db_models::EventUserI> for EventUser {
 }
 
 impl Message {
-    fn from_database_model(message: db_models::Message, chat: db_models::Chat, user: db_models::User, connection: &diesel::PgConnection) -> DieselResult<Message> {
-        let left_chat_member = message
-            .left_chat_member_id
-            .and_then(|id|
-                User::get_by_id(id, connection).ok()
-            ).ok_or(diesel::NotFound)?;
-        let new_chat_members = message
-            .new_chat_member_ids
-            .and_then(|ids|
-                ids
-                    .into_iter()
-                    .map(|id| User::get_by_id(id, connection).ok())
-                    .collect())
-            .unwrap_or(vec![]);
-        let migrate_to_chat = message
-            .migrate_to_chat_id
-            .and_then(|id|
-                Chat::get_by_id(id, connection).ok());
-        let migrate_from_chat = message
-            .migrate_from_chat_id
-            .and_then(|id|
-                Chat::get_by_id(id, connection).ok());
-
-        Ok(Message {
+    /// Related users and chats are only referenced by id, they are resolved through the request's loaders.
+    fn from_database_model(message: db_models::Message, chat: db_models::Chat, user: Option<db_models::User>) -> Message {
+        let chat: Chat = chat.into();
+
+        Message {
             id: message.id,
             telegram_id: message.telegram_id,
-            chat: chat.into(),
-            user: Some(user.into()),
-            timestamp: message.timestamp,
+            timestamp: chat.localize(message.timestamp),
+            edit_timestamp: message.edit_timestamp.map(|edit_timestamp| chat.localize(edit_timestamp)),
+            chat,
+            user,
             reply_to_message_id: message.reply_to_message_id,
-            edit_timestamp: message.edit_timestamp,
             text: message.text,
             caption: message.caption,
-            new_chat_members,
-            left_chat_member: Some(left_chat_member),
+            new_chat_member_ids: message.new_chat_member_ids.unwrap_or_default(),
+            left_chat_member_id: message.left_chat_member_id,
             new_chat_title: message.new_chat_title,
             group_chat_created: message.group_chat_created,
             supergroup_chat_created: message.supergroup_chat_created,
-            migrate_to_chat,
-            migrate_from_chat,
+            migrate_to_chat_id: message.migrate_to_chat_id,
+            migrate_from_chat_id: message.migrate_from_chat_id,
             pinned_message_id: message.pinned_message_id,
-        })
+        }
     }
 
-    fn from_loaded_vec(v: Vec<(db_models::Message, db_models::Chat, db_models::User)>, connection: &diesel::PgConnection) -> DieselResult<Vec<Message>> {
+    fn from_loaded_vec(v: Vec<(db_models::Message, db_models::Chat, Option<db_models::User>)>) -> Vec<Message> {
         v
             .into_iter()
-            .map(|(message, chat, user): (db_models::Message, db_models::Chat, db_models::User)| {
-                Message::from_database_model(message, chat, user, connection)
+            .map(|(message, chat, user): (db_models::Message, db_models::Chat, Option<db_models::User>)| {
+                Message::from_database_model(message, chat, user)
             })
             .collect()
     }
 
+    /// All filters are joined by an AND, `from` and `to` are both inclusive.
+    /// Sorted by `order_by` or the timestamp if it is empty, ties are broken by the id.
+    pub(crate) fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[MessageOrder], chat_id: Option<i32>, user_id: Option<i32>, from: Option<DateTime<FixedOffset>>, to: Option<DateTime<FixedOffset>>) -> DieselResult<Vec<Message>> {
+        use schema::messages::dsl;
+
+        let mut sqlquery = dsl::messages
+            .inner_join(schema::chats::table)
+            .left_join(schema::users::table)
+            .limit(constraints.limit as i64)
+            .offset(constraints.offset as i64)
+            .into_boxed();
+
+        if order_by.is_empty() {
+            sqlquery = sqlquery.then_order_by(dsl::timestamp);
+        }
+        for order in order_by {
+            sqlquery = match order.field {
+                MessageOrderField::ID => then_order_by!(sqlquery, dsl::id, order.direction()),
+                MessageOrderField::TIMESTAMP => then_order_by!(sqlquery, dsl::timestamp, order.direction()),
+            };
+        }
+        sqlquery = sqlquery.then_order_by(dsl::id);
+
+        if let Some(val) = chat_id {
+            sqlquery = sqlquery.filter(dsl::chat_id.eq(val));
+        }
+        if let Some(val) = user_id {
+            sqlquery = sqlquery.filter(dsl::user_id.eq(val));
+        }
+        if let Some(val) = from {
+            sqlquery = sqlquery.filter(dsl::timestamp.ge(val.with_timezone(&Utc)));
+        }
+        if let Some(val) = to {
+            sqlquery = sqlquery.filter(dsl::timestamp.le(val.with_timezone(&Utc)));
+        }
+
+        Ok(Message::from_loaded_vec(sqlquery
+            .load::<(db_models::Message, db_models::Chat, Option<db_models::User>)>(connection)?))
+    }
+
+    /// Number of messages `get` pages through with the same filters.
+    pub(crate) fn count(connection: &diesel::PgConnection, chat_id: Option<i32>, user_id: Option<i32>, from: Option<DateTime<FixedOffset>>, to: Option<DateTime<FixedOffset>>) -> DieselResult<i64> {
+        use schema::messages::dsl;
+
+        // Every message has a chat and the sender is joined optionally, so the joins of `get` don't change the count
+        let mut sqlquery = dsl::messages.into_boxed();
+        if let Some(val) = chat_id {
+            sqlquery = sqlquery.filter(dsl::chat_id.eq(val));
+        }
+        if let Some(val) = user_id {
+            sqlquery = sqlquery.filter(dsl::user_id.eq(val));
+        }
+        if let Some(val) = from {
+            sqlquery = sqlquery.filter(dsl::timestamp.ge(val.with_timezone(&Utc)));
+        }
+        if let Some(val) = to {
+            sqlquery = sqlquery.filter(dsl::timestamp.le(val.with_timezone(&Utc)));
+        }
+
+        sqlquery.count().get_result(connection)
+    }
+
+    pub(crate) fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Message> {
+        use schema::messages::dsl;
+
+        let (message, chat, user) = dsl::messages
+            .filter(dsl::id.eq(id))
+            .inner_join(schema::chats::table)
+            .left_join(schema::users::table)
+            .first::<(db_models::Message, db_models::Chat, Option<db_models::User>)>(connection)?;
+
+        Ok(Message::from_database_model(message, chat, user))
+    }
+
+    /// Messages are referenced by their telegram id, which is only unique within a chat, so they are keyed by
+    /// `(chat_id, telegram_id)`.
+    pub(crate) fn get_by_telegram_ids(keys: &[(i32, i32)], connection: &diesel::PgConnection) -> DieselResult<HashMap<(i32, i32), Message>> {
+        use schema::messages::dsl;
+
+        let chat_ids: Vec<i32> = keys.iter().map(|(chat_id, _)| *chat_id).collect();
+        let telegram_ids: Vec<i32> = keys.iter().map(|(_, telegram_id)| *telegram_id).collect();
+
+        // Filtering both columns separately may load a few messages too many, those are dropped again
+        Ok(Message::from_loaded_vec(dsl::messages
+            .filter(dsl::chat_id.eq(any(chat_ids)))
+            .filter(dsl::telegram_id.eq(any(telegram_ids)))
+            .inner_join(schema::chats::table)
+            .left_join(schema::users::table)
+            .load::<(db_models::Message, db_models::Chat, Option<db_models::User>)>(connection)?)
+            .into_iter()
+            .map(|message| ((message.chat.id, message.telegram_id), message))
+            .filter(|(key, _)| keys.contains(key))
+            .collect())
+    }
+
+    /// Replies keyed by `(chat_id, telegram_id)` of the message they reply to.
+    pub(crate) fn get_replies(keys: &[(i32, i32)], connection: &diesel::PgConnection) -> DieselResult<HashMap<(i32, i32), Vec<Message>>> {
+        use schema::messages::dsl;
+
+        let chat_ids: Vec<i32> = keys.iter().map(|(chat_id, _)| *chat_id).collect();
+        let telegram_ids: Vec<i32> = keys.iter().map(|(_, telegram_id)| *telegram_id).collect();
+
+        let mut replies: HashMap<(i32, i32), Vec<Message>> = HashMap::new();
+        for message in Message::from_loaded_vec(dsl::messages
+            .filter(dsl::chat_id.eq(any(chat_ids)))
+            .filter(dsl::reply_to_message_id.eq(any(telegram_ids)))
+            .inner_join(schema::chats::table)
+            .left_join(schema::users::table)
+            .order((dsl::timestamp, dsl::id))
+            .load::<(db_models::Message, db_models::Chat, Option<db_models::User>)>(connection)?) {
+            if let Some(reply_to_message_id) = message.reply_to_message_id {
+                replies.entry((message.chat.id, reply_to_message_id)).or_default().push(message);
+            }
+        }
+        replies.retain(|key, _| keys.contains(key));
+
+        Ok(replies)
+    }
+
     fn get_by_user_id(user_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<Message>> {
         use schema::messages::dsl;
 
-        Message::from_loaded_vec(dsl::messages
-                                     .filter(dsl::user_id.eq(user_id))
-                                     .inner_join(schema::chats::table)
-                                     .inner_join(schema::users::table)
-                                     .load::<(db_models::Message, db_models::Chat, db_models::User)>(connection)?,
-                                 connection,
-        )
+        Ok(Message::from_loaded_vec(dsl::messages
+            .filter(dsl::user_id.eq(user_id))
+            .inner_join(schema::chats::table)
+            .left_join(schema::users::table)
+            .load::<(db_models::Message, db_models::Chat, Option<db_models::User>)>(connection)?))
     }
 
     fn get_by_chat_id(chat_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<Message>> {
         use schema::messages::dsl;
 
-        Message::from_loaded_vec(dsl::messages
-                                     .filter(dsl::chat_id.eq(chat_id))
-                                     .inner_join(schema::chats::table)
-                                     .inner_join(schema::users::table)
-                                     .load::<(db_models::Message, db_models::Chat, db_models::User)>(connection)?,
-                                 connection)
+        Ok(Message::from_loaded_vec(dsl::messages
+            .filter(dsl::chat_id.eq(chat_id))
+            .inner_join(schema::chats::table)
+            .left_join(schema::users::table)
+            .load::<(db_models::Message, db_models::Chat, Option<db_models::User>)>(connection)?))
     }
 }
 
@@ -530,33 +945,51 @@ impl Into<db_models::MessageI> for Message {
             telegram_id: self.telegram_id,
             chat_id: self.chat.id,
             user_id: self.user.and_then(|user| Some(user.id)),
-            timestamp: self.timestamp,
-//            reply_to_message_id: self.reply_to.and_then(|message| Some(message.into_inner().id as i32)),
+            timestamp: self.timestamp.with_timezone(&Utc),
             reply_to_message_id: self.reply_to_message_id,
-            edit_timestamp: self.edit_timestamp,
+            edit_timestamp: self.edit_timestamp.map(|edit_timestamp| edit_timestamp.with_timezone(&Utc)),
             text: self.text,
             caption: self.caption,
-            new_chat_member_ids: self.new_chat_members.into_iter().map(|user| Some(user.id)).collect(),
-            left_chat_member_id: self.left_chat_member.and_then(|user| Some(user.id)),
+            new_chat_member_ids: Some(self.new_chat_member_ids),
+            left_chat_member_id: self.left_chat_member_id,
             new_chat_title: self.new_chat_title,
             group_chat_created: self.group_chat_created,
             supergroup_chat_created: self.supergroup_chat_created,
-            migrate_to_chat_id: self.migrate_to_chat.and_then(|chat| Some(chat.id)),
-            migrate_from_chat_id: self.migrate_from_chat.and_then(|chat| Some(chat.id)),
-//            pinned_message_id: self.pinned_message.and_then(|message| Some(message.into_inner().id as i32)),
+            migrate_to_chat_id: self.migrate_to_chat_id,
+            migrate_from_chat_id: self.migrate_from_chat_id,
             pinned_message_id: self.pinned_message_id,
         }
     }
 }
 
 impl User {
-    pub fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<User>> {
-        schema::users::table
+    /// Sorted by `order_by`, ties are broken by the id.
+    pub fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[UserOrder]) -> DieselResult<Vec<User>> {
+        use schema::users::dsl;
+
+        let mut query = dsl::users.into_boxed();
+        for order in order_by {
+            query = match order.field {
+                UserOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                UserOrderField::USERNAME => then_order_by!(query, dsl::username, order.direction()),
+                UserOrderField::FIRST_NAME => then_order_by!(query, dsl::first_name, order.direction()),
+                UserOrderField::TELEGRAM_ID => then_order_by!(query, dsl::telegram_id, order.direction()),
+            };
+        }
+
+        query
+            .then_order_by(dsl::id)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
             .load::<db_models::User>(connection)
     }
 
+    pub(crate) fn count(connection: &diesel::PgConnection) -> DieselResult<i64> {
+        schema::users::table
+            .count()
+            .get_result(connection)
+    }
+
     pub(crate) fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<User> {
         use schema::users::dsl;
 
@@ -565,7 +998,18 @@ impl User {
             .first::<db_models::User>(connection)
     }
 
-    fn get_by_telegram_id(telegram_id: i32, connection: &diesel::PgConnection) -> DieselResult<User> {
+    pub(crate) fn get_by_ids(ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, User>> {
+        use schema::users::dsl;
+
+        Ok(dsl::users
+            .filter(dsl::id.eq(any(ids.to_vec())))
+            .load::<db_models::User>(connection)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect())
+    }
+
+    pub(crate) fn get_by_telegram_id(telegram_id: i32, connection: &diesel::PgConnection) -> DieselResult<User> {
         use schema::users::dsl;
 
         dsl::users
@@ -590,7 +1034,7 @@ impl Into<db_models::UserI> for User {
 impl UserRoll {
     pub fn from_database_model(user_roll: db_models::UserRoll, event_user: EventUser) -> UserRoll {
         UserRoll {
-            id: 0,
+            id: user_roll.id,
             event_user,
             jumbo: user_roll.jumbo,
             alcoholic: user_roll.alcoholic,
@@ -599,13 +1043,27 @@ impl UserRoll {
         }
     }
 
-    pub fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<UserRoll>> {
-        Ok(schema::user_rolls::table
+    /// Sorted by `order_by`, ties are broken by the id.
+    pub fn get(connection: &diesel::PgConnection, constraints: Constraints, order_by: &[UserRollOrder]) -> DieselResult<Vec<UserRoll>> {
+        use schema::user_rolls::dsl;
+
+        let mut query = dsl::user_rolls
             .inner_join(schema::event_users::table
                 .inner_join(schema::events::table
                     .inner_join(schema::chats::table)
                 )
                 .inner_join(schema::users::table))
+            .into_boxed();
+        for order in order_by {
+            query = match order.field {
+                UserRollOrderField::ID => then_order_by!(query, dsl::id, order.direction()),
+                UserRollOrderField::ROLL => then_order_by!(query, dsl::roll, order.direction()),
+                UserRollOrderField::DRINK => then_order_by!(query, dsl::drink, order.direction()),
+            };
+        }
+
+        Ok(query
+            .then_order_by(dsl::id)
             .limit(constraints.limit as i64)
             .offset(constraints.offset as i64)
             .load(connection)?
@@ -619,7 +1077,103 @@ impl UserRoll {
             .collect())
     }
 
-    fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<UserRoll> {
+    pub(crate) fn count(connection: &diesel::PgConnection) -> DieselResult<i64> {
+        schema::user_rolls::table
+            .count()
+            .get_result(connection)
+    }
+
+    /// Rolls the dice on the server and stores the resulting drink for `event_user_id`.
+    ///
+    /// A roll is only ever generated once per `(event_user_id, callback_query_id)`, repeated calls return the
+    /// stored roll instead of rolling again.
+    ///
+    /// Users who prefer non-alcoholic drinks always roll non-alcoholic ones, and drinks containing an
+    /// ingredient the user excluded are never rolled.
+    pub fn roll_dice(event_user_id: i32, callback_query_id: String, jumbo: bool, alcoholic: bool, connection: &diesel::PgConnection) -> error::Result<UserRoll> {
+        let event_user = EventUser::get_by_id(event_user_id, connection)?;
+
+        if let Some(user_roll) = db_models::UserRoll::get_by_callback_query(event_user_id, &callback_query_id, connection)? {
+            return Ok(UserRoll::from_database_model(user_roll, event_user));
+        }
+
+        let alcoholic = alcoholic && !event_user.user.non_alcoholic;
+        let candidates = db_models::Cocktail::get_by_flags(event_user.event.effective_menu_id(), event_user.user.id, jumbo, alcoholic, connection)?;
+        if candidates.is_empty() {
+            return Err(error::Error::validation("NO_DRINK_AVAILABLE", "No cocktail on the menu matches the roll"));
+        }
+
+        let mut rng = rand::thread_rng();
+        let roll = rng.gen_range(1, DICE_FACES + 1);
+        let range = UserRoll::candidate_range(roll, candidates.len());
+        let cocktail = &candidates[rng.gen_range(range.start, range.end)];
+
+        let user_roll = db_models::UserRollI {
+            event_user_id,
+            callback_query_id,
+            jumbo,
+            alcoholic,
+            roll,
+            drink: cocktail.name.clone(),
+        }.insert(connection)?;
+
+        Ok(UserRoll::from_database_model(user_roll, event_user))
+    }
+
+    /// Slice of the `count` (category ordered) candidates a dice face stands for.
+    ///
+    /// The faces split the candidates into equally sized slices, so neighbouring faces map to neighbouring
+    /// categories. With fewer candidates than faces, several faces share a candidate.
+    fn candidate_range(roll: i32, count: usize) -> Range<usize> {
+        let start = (roll - 1) as usize * count / DICE_FACES as usize;
+        let end = roll as usize * count / DICE_FACES as usize;
+
+        start..std::cmp::max(end, start + 1)
+    }
+
+    pub fn get_by_event_id(event_id: i32, connection: &diesel::PgConnection) -> DieselResult<Vec<UserRoll>> {
+        Ok(schema::user_rolls::table
+            .inner_join(schema::event_users::table
+                .inner_join(schema::events::table
+                    .inner_join(schema::chats::table)
+                )
+                .inner_join(schema::users::table))
+            .filter(schema::event_users::dsl::event_id.eq(event_id))
+            .load(connection)?
+            .into_iter()
+            .map(|(user_roll, (event_user, (event, chat), user)): (db_models::UserRoll, (db_models::EventUser, (db_models::Event, db_models::Chat), db_models::User))| {
+                let event_user = EventUser::from_database_model(event_user, event, chat, user);
+
+                UserRoll::from_database_model(user_roll, event_user)
+            })
+            .collect())
+    }
+
+    /// Rolls keyed by the id of their event.
+    pub(crate) fn get_by_event_ids(event_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<UserRoll>>> {
+        let mut user_rolls: HashMap<i32, Vec<UserRoll>> = HashMap::new();
+        for user_roll in schema::user_rolls::table
+            .inner_join(schema::event_users::table
+                .inner_join(schema::events::table
+                    .inner_join(schema::chats::table)
+                )
+                .inner_join(schema::users::table))
+            .filter(schema::event_users::dsl::event_id.eq(any(event_ids.to_vec())))
+            .order(schema::user_rolls::dsl::id)
+            .load(connection)?
+            .into_iter()
+            .map(|(user_roll, (event_user, (event, chat), user)): (db_models::UserRoll, (db_models::EventUser, (db_models::Event, db_models::Chat), db_models::User))| {
+                let event_user = EventUser::from_database_model(event_user, event, chat, user);
+
+                UserRoll::from_database_model(user_roll, event_user)
+            }) {
+            user_rolls.entry(user_roll.event_user.event.id).or_default().push(user_roll);
+        }
+
+        Ok(user_rolls)
+    }
+
+    pub(crate) fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<UserRoll> {
         let dbmodel = db_models::UserRoll::get_by_id(id, connection)?;
         let event_user = EventUser::get_by_id(dbmodel.event_user_id, connection)?;
 
@@ -635,19 +1189,51 @@ impl UserRoll {
 }
 
 impl Cocktail {
-    pub fn get(connection: &diesel::PgConnection, constraints: Constraints) -> DieselResult<Vec<Cocktail>> {
-        Ok(db_models::Cocktail::get(connection, constraints)?
+    fn from_database_model(cocktail: db_models::Cocktail) -> Cocktail {
+        Cocktail {
+            id: cocktail.id,
+            name: cocktail.name,
+            jumbo: cocktail.jumbo,
+            alcoholic: cocktail.alcoholic,
+            category_id: cocktail.category,
+            price: cocktail.price,
+            jumbo_price: cocktail.jumbo_price,
+            retired: cocktail.retired,
+            menu_id: cocktail.menu_id,
+        }
+    }
+
+    pub fn get_by_id(id: i32, connection: &diesel::PgConnection) -> DieselResult<Cocktail> {
+        Ok(Cocktail::from_database_model(db_models::Cocktail::get_by_id(id, connection)?))
+    }
+
+    pub fn get_by_name(menu_id: i32, name: &str, connection: &diesel::PgConnection) -> DieselResult<Option<Cocktail>> {
+        Ok(db_models::Cocktail::get_by_name(menu_id, name, connection)?
+            .map(Cocktail::from_database_model))
+    }
+
+    /// Cocktails keyed by their menu id and name, the one with the lowest id wins if a name is taken twice.
+    pub(crate) fn get_by_names(keys: &[(i32, String)], connection: &diesel::PgConnection) -> DieselResult<HashMap<(i32, String), Cocktail>> {
+        let menu_ids: Vec<i32> = keys.iter().map(|(menu_id, _)| *menu_id).collect();
+        let names: Vec<String> = keys.iter().map(|(_, name)| name.clone()).collect();
+
+        let mut cocktails = HashMap::new();
+        for cocktail in schema::cocktails::table
+            .filter(schema::cocktails::dsl::menu_id.eq(any(menu_ids)))
+            .filter(schema::cocktails::dsl::name.eq(any(names)))
+            .order(schema::cocktails::dsl::id)
+            .load::<db_models::Cocktail>(connection)? {
+            let key = (cocktail.menu_id, cocktail.name.clone());
+            cocktails.entry(key).or_insert_with(|| Cocktail::from_database_model(cocktail));
+        }
+
+        Ok(cocktails)
+    }
+
+    pub fn get(connection: &diesel::PgConnection, constraints: Constraints, filter: CocktailFilter, order_by: &[CocktailOrder], menu_id: Option<i32>, include_retired: bool) -> DieselResult<Vec<Cocktail>> {
+        Ok(db_models::Cocktail::get(connection, constraints, filter, order_by, menu_id, include_retired)?
             .into_iter()
-            .filter_map(|cocktail| {
-                Some(Cocktail {
-                    id: cocktail.id,
-                    name: cocktail.name,
-                    jumbo: cocktail.jumbo,
-                    alcoholic: cocktail.alcoholic,
-                    category: CocktailCategory::from(cocktail.category).to_string(),
-                    ingredients: CocktailIngredient::get_by_cocktail(cocktail.id, connection, constraints).ok()?,
-                })
-            })
+            .map(Cocktail::from_database_model)
             .collect())
     }
 }
@@ -667,4 +1253,77 @@ impl CocktailIngredient {
             })
             .collect())
     }
+
+    pub(crate) fn get_by_cocktails(cocktail_ids: &[i32], connection: &diesel::PgConnection) -> DieselResult<HashMap<i32, Vec<Ingredient>>> {
+        let mut ingredients: HashMap<i32, Vec<Ingredient>> = HashMap::new();
+        for (cocktail_ingredient, ingredient) in schema::cocktail_ingredients::table
+            .inner_join(schema::ingredients::table)
+            .filter(schema::cocktail_ingredients::cocktail_id.eq(any(cocktail_ids.to_vec())))
+            .order(schema::ingredients::id)
+            .load::<(db_models::CocktailIngredient, db_models::Ingredient)>(connection)? {
+            ingredients.entry(cocktail_ingredient.cocktail_id).or_default().push(ingredient);
+        }
+
+        Ok(ingredients)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{BillRule, ChatType, DICE_FACES, KeyboardType, UserRoll, Weekday};
+
+    macro_rules! assert_round_trip {
+        ($name:ident { $($variant:ident),+ }, $unknown:expr) => {
+            let variants = [$($name::$variant),+];
+            for (index, variant) in variants.iter().enumerate() {
+                let value = i16::from(*variant);
+                assert_eq!(value, index as i16);
+                assert_eq!($name::try_from(value).unwrap(), *variant);
+            }
+
+            for value in &[-1, $unknown, i16::max_value()] {
+                let error = $name::try_from(*value).unwrap_err();
+                assert_eq!(error.enum_name, stringify!($name));
+                assert_eq!(error.value, *value);
+            }
+        };
+    }
+
+    #[test]
+    fn candidate_ranges_cover_every_candidate() {
+        for count in 1..20 {
+            let ranges: Vec<_> = (1..=DICE_FACES).map(|roll| UserRoll::candidate_range(roll, count)).collect();
+
+            assert_eq!(ranges[0].start, 0);
+            assert_eq!(ranges[ranges.len() - 1].end, count);
+            for range in &ranges {
+                assert!(range.start < range.end && range.end <= count);
+            }
+            for pair in ranges.windows(2) {
+                assert!(pair[0].end >= pair[1].start && pair[0].start <= pair[1].start);
+            }
+        }
+    }
+
+    #[test]
+    fn chat_type_round_trip() {
+        assert_round_trip!(ChatType { PRIVATE, GROUP, SUPERGROUP }, 3);
+    }
+
+    #[test]
+    fn keyboard_type_round_trip() {
+        assert_round_trip!(KeyboardType { NONE, ATTEND, DICE }, 3);
+    }
+
+    #[test]
+    fn bill_rule_round_trip() {
+        assert_round_trip!(BillRule { OWN_DRINKS, LOWEST_ROLL_PAYS, SPLIT_EVENLY }, 3);
+    }
+
+    #[test]
+    fn weekday_round_trip() {
+        assert_round_trip!(Weekday { MONDAY, TUESDAY, WEDNESDAY, THURSDAY, FRIDAY, SATURDAY, SUNDAY }, 7);
+    }
 }
@@ -1,10 +1,15 @@
+use std::collections::BTreeMap;
+
 use juniper::RootNode;
 use rocket::{get, post};
+use rocket::http::{Cookie, Cookies, Status};
 use rocket::response::content;
 use rocket::State;
 
+use crate::auth::{self, AuthError, Credentials, TelegramBotToken};
 use crate::db::PrimaryDb;
 use crate::graphql::{Context, MutationRoot, QueryRoot};
+use crate::models::User;
 
 pub type Schema = RootNode<'static, QueryRoot, MutationRoot>;
 
@@ -16,17 +21,72 @@ pub fn graphiql() -> content::Html<String> {
 #[get("/graphql?<request>")]
 pub fn get_graphql_handler(
     context: PrimaryDb,
+    credentials: Credentials,
     request: juniper_rocket::GraphQLRequest,
     schema: State<Schema>,
-) -> juniper_rocket::GraphQLResponse {
-    request.execute(&schema, &Context { connection: context })
+) -> Result<juniper_rocket::GraphQLResponse, Status> {
+    execute(request, &schema, context, credentials)
 }
 
 #[post("/graphql", data = "<request>")]
 pub fn post_graphql_handler(
     context: PrimaryDb,
+    credentials: Credentials,
     request: juniper_rocket::GraphQLRequest,
     schema: State<Schema>,
-) -> juniper_rocket::GraphQLResponse {
-    request.execute(&schema, &Context { connection: context })
+) -> Result<juniper_rocket::GraphQLResponse, Status> {
+    execute(request, &schema, context, credentials)
+}
+
+fn execute(request: juniper_rocket::GraphQLRequest, schema: &Schema, connection: PrimaryDb, credentials: Credentials) -> Result<juniper_rocket::GraphQLResponse, Status> {
+    match Context::new(connection, credentials) {
+        Ok(context) => Ok(request.execute(schema, &context)),
+        Err(AuthError::Database) => Err(Status::InternalServerError),
+        Err(_) => Err(Status::Unauthorized),
+    }
+}
+
+/// Accepts the url encoded data of the telegram login widget.
+#[post("/auth/telegram/login", data = "<data>")]
+pub fn telegram_login(
+    connection: PrimaryDb,
+    cookies: Cookies,
+    bot_token: State<TelegramBotToken>,
+    data: String,
+) -> Result<Status, Status> {
+    login(connection, cookies, bot_token, &data, auth::verify_login_widget)
+}
+
+/// Accepts the raw `initData` of a telegram web app.
+#[post("/auth/telegram/web-app", data = "<data>")]
+pub fn telegram_web_app_login(
+    connection: PrimaryDb,
+    cookies: Cookies,
+    bot_token: State<TelegramBotToken>,
+    data: String,
+) -> Result<Status, Status> {
+    login(connection, cookies, bot_token, &data, auth::verify_web_app)
+}
+
+#[post("/auth/logout")]
+pub fn logout(mut cookies: Cookies) -> Status {
+    cookies.remove_private(Cookie::named(auth::SESSION_COOKIE));
+
+    Status::NoContent
+}
+
+fn login<F>(connection: PrimaryDb, mut cookies: Cookies, bot_token: State<TelegramBotToken>, data: &str, verify: F) -> Result<Status, Status>
+    where F: Fn(&BTreeMap<String, String>, &str) -> Result<i32, AuthError> {
+    let bot_token = bot_token.0.as_ref().ok_or(Status::ServiceUnavailable)?;
+    let telegram_id = verify(&auth::parse_fields(data), bot_token).map_err(|_| Status::Unauthorized)?;
+
+    // Only users the bot has already seen are able to log in
+    let user = match User::get_by_telegram_id(telegram_id, &connection.0) {
+        Ok(user) => user,
+        Err(diesel::NotFound) => return Err(Status::Forbidden),
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    cookies.add_private(Cookie::new(auth::SESSION_COOKIE, user.id.to_string()));
+
+    Ok(Status::NoContent)
 }
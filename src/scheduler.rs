@@ -0,0 +1,150 @@
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use chrono_tz::Tz;
+use diesel::Connection;
+
+use crate::database::models::{Event, EventI, EventSchedule};
+
+type DieselResult<T> = Result<T, diesel::result::Error>;
+
+/// Returns the first time after `now` the schedule takes place.
+///
+/// Times skipped by a daylight saving transition move on to the following week.
+pub fn next_occurrence(schedule: &EventSchedule, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let timezone: Tz = schedule.timezone.parse().ok()?;
+    let weekday: chrono::Weekday = schedule.weekday.into();
+    let today = now.with_timezone(&timezone).date().naive_local();
+
+    // Two weeks and a day cover today's occurrence having already passed and the next one being skipped
+    (0..15)
+        .map(|days| today + chrono::Duration::days(days))
+        .filter(|date| date.weekday() == weekday)
+        .filter_map(|date| timezone.from_local_datetime(&date.and_time(schedule.time)).earliest())
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .find(|datetime| *datetime > now)
+}
+
+/// Creates the next event of `schedule` unless it already exists.
+pub fn materialize_event(schedule: &EventSchedule, now: DateTime<Utc>, connection: &diesel::PgConnection) -> DieselResult<Option<Event>> {
+    match next_occurrence(schedule, now) {
+        Some(timestamp) => Ok(Some(EventI {
+            chat_id: schedule.chat_id,
+            timestamp,
+            active: false,
+            menu_id: None,
+        }.insert(connection)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn materialize_events(connection: &diesel::PgConnection) -> DieselResult<()> {
+    let now = Utc::now();
+    for schedule in EventSchedule::get_all(connection)? {
+        materialize_event(&schedule, now, connection)?;
+    }
+
+    Ok(())
+}
+
+/// Materializes the next event of every schedule each `interval` for as long as the process runs.
+pub fn spawn(database_url: String, interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        // A new connection each run, so a dropped one doesn't stop the job for good
+        match diesel::PgConnection::establish(&database_url) {
+            Ok(connection) => if let Err(error) = materialize_events(&connection) {
+                error!("Failed to materialize scheduled events: {}", error);
+            },
+            Err(error) => error!("Failed to connect to the database for scheduled events: {}", error),
+        }
+
+        thread::sleep(interval);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+
+    use crate::database::models::EventSchedule;
+    use crate::models::Weekday;
+
+    use super::next_occurrence;
+
+    fn schedule(weekday: Weekday, time: &str, timezone: &str) -> EventSchedule {
+        EventSchedule {
+            id: 1,
+            chat_id: 1,
+            weekday,
+            time: NaiveTime::parse_from_str(time, "%H:%M").unwrap(),
+            timezone: timezone.to_owned(),
+        }
+    }
+
+    fn utc(datetime: &str) -> DateTime<Utc> {
+        Utc.datetime_from_str(datetime, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn later_today() {
+        // 2020-06-01 is a Monday
+        let schedule = schedule(Weekday::MONDAY, "19:00", "UTC");
+
+        assert_eq!(next_occurrence(&schedule, utc("2020-06-01 10:00")), Some(utc("2020-06-01 19:00")));
+    }
+
+    #[test]
+    fn today_but_already_past() {
+        let schedule = schedule(Weekday::MONDAY, "19:00", "UTC");
+
+        assert_eq!(next_occurrence(&schedule, utc("2020-06-01 20:00")), Some(utc("2020-06-08 19:00")));
+        assert_eq!(next_occurrence(&schedule, utc("2020-06-01 19:00")), Some(utc("2020-06-08 19:00")));
+    }
+
+    #[test]
+    fn later_this_week() {
+        let schedule = schedule(Weekday::FRIDAY, "19:00", "UTC");
+
+        assert_eq!(next_occurrence(&schedule, utc("2020-06-01 20:00")), Some(utc("2020-06-05 19:00")));
+    }
+
+    #[test]
+    fn weekday_in_the_timezone_of_the_schedule() {
+        let schedule = schedule(Weekday::MONDAY, "19:00", "Europe/Berlin");
+
+        // Already Monday in Berlin, which is two hours ahead in summer
+        assert_eq!(next_occurrence(&schedule, utc("2020-05-31 23:30")), Some(utc("2020-06-01 17:00")));
+        assert_eq!(next_occurrence(&schedule, utc("2020-06-01 17:30")), Some(utc("2020-06-08 17:00")));
+    }
+
+    #[test]
+    fn follows_daylight_saving_time() {
+        let schedule = schedule(Weekday::SUNDAY, "19:00", "Europe/Berlin");
+
+        // Berlin switches to summer time on 2020-03-29 and back on 2020-10-25
+        assert_eq!(next_occurrence(&schedule, utc("2020-03-28 12:00")), Some(utc("2020-03-29 17:00")));
+        assert_eq!(next_occurrence(&schedule, utc("2020-10-24 12:00")), Some(utc("2020-10-25 18:00")));
+    }
+
+    #[test]
+    fn skipped_time_moves_on_to_the_following_week() {
+        let schedule = schedule(Weekday::SUNDAY, "02:30", "Europe/Berlin");
+
+        assert_eq!(next_occurrence(&schedule, utc("2020-03-28 12:00")), Some(utc("2020-04-05 00:30")));
+    }
+
+    #[test]
+    fn repeated_time_takes_the_earlier_one() {
+        let schedule = schedule(Weekday::SUNDAY, "02:30", "Europe/Berlin");
+
+        assert_eq!(next_occurrence(&schedule, utc("2020-10-24 12:00")), Some(utc("2020-10-25 00:30")));
+    }
+
+    #[test]
+    fn unknown_timezone() {
+        let schedule = schedule(Weekday::MONDAY, "19:00", "Mars/Olympus_Mons");
+
+        assert_eq!(next_occurrence(&schedule, utc("2020-06-01 10:00")), None);
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use diesel::dsl::any;
+use diesel::prelude::*;
+use diesel::sql_types::{Float8, Int4, Int8, Nullable, Timestamptz};
+
+use crate::database::models as db_models;
+use crate::database::schema;
+use crate::graphql::Context;
+use crate::graphql::schema::Constraints;
+use crate::models::{Chat, DICE_FACES, User};
+
+type DieselResult<T> = Result<T, diesel::result::Error>;
+
+#[derive(Debug, GraphQLObject)]
+pub struct RollCount {
+    pub roll: i32,
+    pub count: i32,
+}
+
+#[derive(Debug, GraphQLObject)]
+pub struct DrinkCount {
+    pub drink: String,
+    pub count: i32,
+}
+
+#[derive(Debug, GraphQLObject)]
+pub struct CategoryCount {
+    pub category: String,
+    pub count: i32,
+}
+
+#[derive(Debug, GraphQLObject)]
+pub struct UserStats {
+    pub total_rolls: i32,
+    #[graphql(description = "How often each dice value was rolled, contains every value from 1 to 6")]
+    pub rolls: Vec<RollCount>,
+    #[graphql(description = "Most rolled drinks first")]
+    pub drinks: Vec<DrinkCount>,
+    #[graphql(description = "Most rolled categories first, drinks which are no longer on the menu are left out")]
+    pub categories: Vec<CategoryCount>,
+    pub jumbo_rolls: i32,
+    pub alcoholic_rolls: i32,
+    #[graphql(description = "Events which already started")]
+    pub events_invited: i32,
+    pub events_attended: i32,
+    #[graphql(description = "Attended events in a row, up to the latest event")]
+    pub current_streak: i32,
+    pub longest_streak: i32,
+}
+
+/// Filters shared by all statistics, `from` and `to` are both inclusive.
+#[derive(Clone, Copy, Debug)]
+pub struct StatsFilter {
+    pub chat_id: Option<i32>,
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+
+impl UserStats {
+    pub fn get(user_id: i32, filter: StatsFilter, connection: &diesel::PgConnection) -> DieselResult<UserStats> {
+        let user_rolls = get_user_rolls(user_id, filter, connection)?;
+        let attendance = get_attendance(user_id, filter, connection)?;
+
+        let mut rolls = vec![0; DICE_FACES as usize];
+        let mut drinks: HashMap<String, i32> = HashMap::new();
+        let mut menu_drinks: HashMap<(i32, String), i32> = HashMap::new();
+        for (user_roll, menu_id) in &user_rolls {
+            if user_roll.roll >= 1 && user_roll.roll <= DICE_FACES {
+                rolls[(user_roll.roll - 1) as usize] += 1;
+            }
+            *drinks.entry(user_roll.drink.clone()).or_default() += 1;
+            *menu_drinks.entry((*menu_id, user_roll.drink.clone())).or_default() += 1;
+        }
+
+        // A drink is categorized by the menu of its event, the same name may belong to another category elsewhere
+        let drink_categories: HashMap<(i32, String), String> = schema::cocktails::table
+            .inner_join(schema::cocktail_categories::table)
+            .filter(schema::cocktails::dsl::menu_id.eq(any(menu_drinks.keys().map(|(menu_id, _)| *menu_id).collect::<Vec<i32>>())))
+            .filter(schema::cocktails::dsl::name.eq(any(drinks.keys().cloned().collect::<Vec<String>>())))
+            .select((schema::cocktails::dsl::menu_id, schema::cocktails::dsl::name, schema::cocktail_categories::dsl::name))
+            .load::<(i32, String, String)>(connection)?
+            .into_iter()
+            .map(|(menu_id, drink, category)| ((menu_id, drink), category))
+            .collect();
+        let mut categories: HashMap<String, i32> = HashMap::new();
+        for (drink, count) in menu_drinks {
+            if let Some(category) = drink_categories.get(&drink) {
+                *categories.entry(category.clone()).or_default() += count;
+            }
+        }
+
+        let (current_streak, longest_streak) = streaks(&attendance);
+
+        Ok(UserStats {
+            total_rolls: user_rolls.len() as i32,
+            rolls: rolls
+                .into_iter()
+                .enumerate()
+                .map(|(index, count)| RollCount { roll: index as i32 + 1, count })
+                .collect(),
+            drinks: sorted_counts(drinks)
+                .into_iter()
+                .map(|(drink, count)| DrinkCount { drink, count })
+                .collect(),
+            categories: sorted_counts(categories)
+                .into_iter()
+                .map(|(category, count)| CategoryCount { category, count })
+                .collect(),
+            jumbo_rolls: user_rolls.iter().filter(|(user_roll, _)| user_roll.jumbo).count() as i32,
+            alcoholic_rolls: user_rolls.iter().filter(|(user_roll, _)| user_roll.alcoholic).count() as i32,
+            events_invited: attendance.len() as i32,
+            events_attended: attendance.iter().filter(|attends| **attends).count() as i32,
+            current_streak,
+            longest_streak,
+        })
+    }
+}
+
+/// Rolls of the user together with the effective menu of their event.
+fn get_user_rolls(user_id: i32, filter: StatsFilter, connection: &diesel::PgConnection) -> DieselResult<Vec<(db_models::UserRoll, i32)>> {
+    let mut query = schema::user_rolls::table
+        .inner_join(schema::event_users::table
+            .inner_join(schema::events::table
+                .inner_join(schema::chats::table)))
+        .filter(schema::event_users::dsl::user_id.eq(user_id))
+        .select((schema::user_rolls::all_columns, schema::events::dsl::menu_id, schema::chats::dsl::menu_id))
+        .into_boxed();
+
+    if let Some(chat_id) = filter.chat_id {
+        query = query.filter(schema::events::dsl::chat_id.eq(chat_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(schema::events::dsl::timestamp.ge(from.with_timezone(&Utc)));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(schema::events::dsl::timestamp.le(to.with_timezone(&Utc)));
+    }
+
+    Ok(query
+        .load::<(db_models::UserRoll, Option<i32>, i32)>(connection)?
+        .into_iter()
+        .map(|(user_roll, event_menu_id, chat_menu_id)| (user_roll, event_menu_id.unwrap_or(chat_menu_id)))
+        .collect())
+}
+
+/// Whether the user attended each event they were invited to, oldest first.
+fn get_attendance(user_id: i32, filter: StatsFilter, connection: &diesel::PgConnection) -> DieselResult<Vec<bool>> {
+    let mut query = schema::event_users::table
+        .inner_join(schema::events::table)
+        .filter(schema::event_users::dsl::user_id.eq(user_id))
+        .filter(schema::events::dsl::timestamp.le(Utc::now()))
+        .order(schema::events::dsl::timestamp)
+        .select(schema::event_users::dsl::attends)
+        .into_boxed();
+
+    if let Some(chat_id) = filter.chat_id {
+        query = query.filter(schema::events::dsl::chat_id.eq(chat_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(schema::events::dsl::timestamp.ge(from.with_timezone(&Utc)));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(schema::events::dsl::timestamp.le(to.with_timezone(&Utc)));
+    }
+
+    query.load(connection)
+}
+
+/// Returns the current and the longest run of attended events.
+fn streaks(attendance: &[bool]) -> (i32, i32) {
+    let mut current = 0;
+    let mut longest = 0;
+    for attends in attendance {
+        current = if *attends { current + 1 } else { 0 };
+        longest = std::cmp::max(longest, current);
+    }
+
+    (current, longest)
+}
+
+/// Highest count first, ties are ordered by name.
+fn sorted_counts(counts: HashMap<String, i32>) -> Vec<(String, i32)> {
+    let mut counts: Vec<(String, i32)> = counts.into_iter().collect();
+    counts.sort_by(|(a_name, a_count), (b_name, b_count)| b_count.cmp(a_count).then_with(|| a_name.cmp(b_name)));
+
+    counts
+}
+
+#[derive(Clone, Copy, Debug, GraphQLEnum)]
+pub enum LeaderboardMetric {
+    #[graphql(description = "Number of rolls")]
+    DRINKS,
+    #[graphql(description = "Number of jumbo rolls")]
+    JUMBOS,
+    #[graphql(description = "Average dice value, users without rolls are left out")]
+    AVERAGE_ROLL,
+    #[graphql(description = "Number of attended events")]
+    ATTENDANCE,
+}
+
+impl LeaderboardMetric {
+    /// Aggregate over `event_users` left joined with their `user_rolls`.
+    fn aggregate(self) -> &'static str {
+        match self {
+            LeaderboardMetric::DRINKS => "COUNT(user_rolls.id)::float8",
+            LeaderboardMetric::JUMBOS => "(COUNT(user_rolls.id) FILTER (WHERE user_rolls.jumbo))::float8",
+            LeaderboardMetric::AVERAGE_ROLL => "AVG(user_rolls.roll)::float8",
+            LeaderboardMetric::ATTENDANCE => "(COUNT(DISTINCT events.id) FILTER (WHERE event_users.attends))::float8",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, GraphQLEnum)]
+pub enum LeaderboardPeriod {
+    #[graphql(description = "The latest event which already started")]
+    EVENT,
+    #[graphql(description = "The current calendar month in the timezone of the chat")]
+    MONTH,
+    #[graphql(description = "The current calendar year in the timezone of the chat")]
+    YEAR,
+    ALL_TIME,
+}
+
+#[derive(Debug, GraphQLObject)]
+#[graphql(Context = Context)]
+pub struct LeaderboardEntry {
+    #[graphql(description = "Users with the same value share a rank, the following rank is skipped")]
+    pub rank: i32,
+    pub user: User,
+    pub value: f64,
+}
+
+#[derive(QueryableByName)]
+struct LeaderboardRow {
+    #[sql_type = "Int4"]
+    user_id: i32,
+    #[sql_type = "Float8"]
+    value: f64,
+    #[sql_type = "Int8"]
+    rank: i64,
+}
+
+impl LeaderboardEntry {
+    /// Ranks the users of a chat by `metric` over events of `period` which already started.
+    pub fn get(chat_id: i32, metric: LeaderboardMetric, period: LeaderboardPeriod, constraints: Constraints, connection: &diesel::PgConnection) -> DieselResult<Vec<LeaderboardEntry>> {
+        let chat = Chat::get_by_id(chat_id, connection)?;
+        let now = Utc::now();
+
+        let (from, event_id) = match period {
+            LeaderboardPeriod::EVENT => match latest_event_id(chat_id, now, connection)? {
+                Some(event_id) => (None, Some(event_id)),
+                None => return Ok(vec![]),
+            },
+            LeaderboardPeriod::MONTH => {
+                let today = now.with_timezone(&timezone(&chat)).date().naive_local();
+                (start_of_day(&chat, NaiveDate::from_ymd(today.year(), today.month(), 1)), None)
+            }
+            LeaderboardPeriod::YEAR => {
+                let today = now.with_timezone(&timezone(&chat)).date().naive_local();
+                (start_of_day(&chat, NaiveDate::from_ymd(today.year(), 1, 1)), None)
+            }
+            LeaderboardPeriod::ALL_TIME => (None, None),
+        };
+
+        // Window functions can't be expressed with diesel's query builder
+        let rows = diesel::sql_query(format!("\
+            SELECT user_id, value, RANK() OVER (ORDER BY value DESC) AS rank \
+            FROM (\
+                SELECT event_users.user_id AS user_id, {} AS value \
+                FROM event_users \
+                INNER JOIN events ON events.id = event_users.event_id \
+                LEFT JOIN user_rolls ON user_rolls.event_user_id = event_users.id \
+                WHERE events.chat_id = $1 \
+                AND events.timestamp <= $2 \
+                AND ($3 IS NULL OR events.timestamp >= $3) \
+                AND ($4 IS NULL OR events.id = $4) \
+                GROUP BY event_users.user_id\
+            ) AS totals \
+            WHERE value IS NOT NULL \
+            ORDER BY rank, user_id \
+            LIMIT $5 OFFSET $6", metric.aggregate()))
+            .bind::<Int4, _>(chat_id)
+            .bind::<Timestamptz, _>(now)
+            .bind::<Nullable<Timestamptz>, _>(from)
+            .bind::<Nullable<Int4>, _>(event_id)
+            .bind::<Int8, _>(constraints.limit as i64)
+            .bind::<Int8, _>(constraints.offset as i64)
+            .load::<LeaderboardRow>(connection)?;
+
+        let mut users = User::get_by_ids(&rows.iter().map(|row| row.user_id).collect::<Vec<i32>>(), connection)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some(LeaderboardEntry {
+                rank: row.rank as i32,
+                user: users.remove(&row.user_id)?,
+                value: row.value,
+            }))
+            .collect())
+    }
+}
+
+fn latest_event_id(chat_id: i32, now: DateTime<Utc>, connection: &diesel::PgConnection) -> DieselResult<Option<i32>> {
+    use schema::events::dsl;
+
+    dsl::events
+        .filter(dsl::chat_id.eq(chat_id))
+        .filter(dsl::timestamp.le(now))
+        .order(dsl::timestamp.desc())
+        .select(dsl::id)
+        .first(connection)
+        .optional()
+}
+
+fn timezone(chat: &Chat) -> Tz {
+    chat.timezone.parse().unwrap_or(Tz::UTC)
+}
+
+fn start_of_day(chat: &Chat, date: NaiveDate) -> Option<DateTime<Utc>> {
+    timezone(chat)
+        .from_local_datetime(&date.and_hms(0, 0, 0))
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}